@@ -20,6 +20,7 @@ use std::iter::{DoubleEndedIterator, FusedIterator};
 use std::str::FromStr;
 use std::vec::IntoIter;
 
+use chrono::{DateTime, FixedOffset, NaiveDateTime, ParseError};
 use serde::de::{DeserializeOwned, Error as DeserError};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
@@ -38,6 +39,23 @@ pub struct Ref {
     pub links: Vec<Link>,
 }
 
+/// Links to a resource and its neighbours in a paginated collection.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct ResourceLinks {
+    #[serde(rename = "self", default)]
+    pub self_link: Option<Url>,
+    #[serde(default)]
+    pub next: Option<Url>,
+    #[serde(default)]
+    pub previous: Option<Url>,
+}
+
+/// A reference to an ID.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IdRef {
+    pub id: String,
+}
+
 /// A reference to an ID and name.
 #[derive(Clone, Debug, Deserialize)]
 pub struct IdAndName {
@@ -86,7 +104,7 @@ impl Eq for Version {}
 
 impl PartialOrd for Version {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -261,12 +279,8 @@ where
         D: Deserializer<'de>,
     {
         let value: String = Deserialize::deserialize(deserializer)?;
-        let version_part = if value.starts_with('v') {
-            &value[1..]
-        } else {
-            &value
-        };
-        XdotY::from_str(&version_part).map_err(D::Error::custom)
+        let version_part = value.strip_prefix('v').unwrap_or(&value);
+        XdotY::from_str(version_part).map_err(D::Error::custom)
     }
 }
 
@@ -289,14 +303,50 @@ where
     }
 }
 
+fn parse_datetime_as_utc(s: &str) -> Result<DateTime<FixedOffset>, ParseError> {
+    if let Ok(result) = DateTime::parse_from_rfc3339(s) {
+        Ok(result)
+    } else {
+        let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")?;
+        Ok(naive.and_utc().into())
+    }
+}
+
+/// Deserialize a date and time, treating values without a time zone as UTC.
+pub fn datetime_as_utc<'de, D>(des: D) -> Result<DateTime<FixedOffset>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: String = Deserialize::deserialize(des)?;
+    parse_datetime_as_utc(&value).map_err(D::Error::custom)
+}
+
+/// Deserialize an optional date and time, treating values without a time zone as UTC.
+pub fn opt_datetime_as_utc<'de, D>(des: D) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<String> = Deserialize::deserialize(des)?;
+    if let Some(s) = value {
+        parse_datetime_as_utc(&s)
+            .map(Some)
+            .map_err(D::Error::custom)
+    } else {
+        Ok(None)
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use std::str::FromStr;
 
+    use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
     use serde_json;
 
-    use super::{empty_as_default, Root, Version, XdotY};
+    use chrono::{DateTime, FixedOffset};
+
+    use super::{empty_as_default, opt_datetime_as_utc, Root, Version, XdotY};
 
     pub fn compare<T: Serialize>(sample: &str, value: T) {
         let converted: serde_json::Value = serde_json::from_str(sample).unwrap();
@@ -304,6 +354,11 @@ pub mod test {
         assert_eq!(result, converted);
     }
 
+    pub fn round_trip<T: Serialize + DeserializeOwned>(sample: &str) {
+        let value: T = serde_json::from_str(sample).unwrap();
+        compare(sample, value);
+    }
+
     #[derive(Debug, Deserialize)]
     struct Custom(bool);
 
@@ -339,6 +394,32 @@ pub mod test {
         assert!(r.string.is_none());
     }
 
+    #[derive(Debug, Deserialize)]
+    struct OptDateTime {
+        #[serde(deserialize_with = "opt_datetime_as_utc", default)]
+        value: Option<DateTime<FixedOffset>>,
+    }
+
+    #[test]
+    fn test_opt_datetime_as_utc() {
+        let expected = DateTime::parse_from_rfc3339("2016-11-06T15:32:17Z").unwrap();
+        for s in &[
+            "{\"value\": \"2016-11-06T15:32:17.000000Z\"}",
+            "{\"value\": \"2016-11-06T17:32:17+02:00\"}",
+            "{\"value\": \"2016-11-06T15:32:17.000000\"}",
+            "{\"value\": \"2016-11-06T15:32:17\"}",
+        ] {
+            let r: OptDateTime = serde_json::from_str(s).unwrap();
+            assert_eq!(r.value.unwrap(), expected);
+        }
+        for s in &["{\"value\": null}", "{}"] {
+            let r: OptDateTime = serde_json::from_str(s).unwrap();
+            assert!(r.value.is_none());
+        }
+        let res: Result<OptDateTime, _> = serde_json::from_str("{\"value\": \"yesterday\"}");
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_xdoty_debug() {
         let xy = XdotY(1, 2);
@@ -0,0 +1,1064 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cloud configuration in the openstacksdk `clouds.yaml` format.
+//!
+//! Cloud entries are merged from `clouds.yaml` and `secure.yaml` (the latter taking precedence),
+//! on top of the profile from `clouds-public.yaml` if the entry has a `profile`.
+//!
+//! Configuration can also be loaded from `OS_*` environment variables or an openrc file.
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+
+use super::common::IdRef;
+use super::identity::{
+    ApplicationCredential, Auth, EndpointFilter, IdOrName, Identity, Interface, Project, Scope,
+    User, UserAndPasscode, UserAndPassword, REDACTED,
+};
+use super::service_types;
+
+mod environment;
+
+pub use self::environment::parse_openrc;
+
+/// Authentication parameters of a cloud (the `auth` section).
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub auth_url: Option<String>,
+    #[serde(alias = "user_name")]
+    pub username: Option<String>,
+    pub user_id: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    pub passcode: Option<String>,
+    #[serde(alias = "tenant_name")]
+    pub project_name: Option<String>,
+    #[serde(alias = "tenant_id")]
+    pub project_id: Option<String>,
+    pub project_domain_name: Option<String>,
+    pub project_domain_id: Option<String>,
+    pub user_domain_name: Option<String>,
+    pub user_domain_id: Option<String>,
+    pub domain_name: Option<String>,
+    pub domain_id: Option<String>,
+    /// Domain ID used for users and projects when their domain is not provided.
+    pub default_domain: Option<String>,
+    pub application_credential_id: Option<String>,
+    pub application_credential_name: Option<String>,
+    pub application_credential_secret: Option<String>,
+    pub system_scope: Option<String>,
+    pub trust_id: Option<String>,
+    /// Authentication types to combine for the `v3multifactor` authentication type.
+    pub auth_methods: Vec<String>,
+}
+
+/// Configuration of a single cloud.
+#[derive(Clone, Debug, Default)]
+pub struct CloudConfig {
+    /// Name of the cloud.
+    pub name: String,
+    /// Name of the vendor profile the configuration is based on.
+    pub profile: Option<String>,
+    /// Authentication type (`password` if `None`).
+    pub auth_type: Option<String>,
+    pub auth: AuthConfig,
+    pub region_name: Option<String>,
+    pub interface: Option<Interface>,
+    pub verify: Option<bool>,
+    pub cacert: Option<String>,
+    /// API versions by service type (from the `<service type>_api_version` keys).
+    ///
+    /// Microversions must be quoted in YAML, unquoted floating point values are rejected.
+    pub api_versions: HashMap<String, String>,
+    /// Endpoint overrides by service type (from the `<service type>_endpoint_override` keys).
+    pub endpoint_overrides: HashMap<String, String>,
+}
+
+/// Contents of `clouds.yaml` merged with `secure.yaml` and `clouds-public.yaml`.
+#[derive(Clone, Debug, Default)]
+pub struct CloudsYaml {
+    clouds: Mapping,
+    public_clouds: Mapping,
+}
+
+/// Error when parsing or interpreting cloud configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Configuration is not valid YAML or has an unexpected structure.
+    InvalidFormat(String),
+    /// Configuration file cannot be read.
+    FileError {
+        /// Path to the file.
+        path: String,
+        /// Error message.
+        message: String,
+    },
+    /// No cloud with this name.
+    CloudNotFound {
+        /// Requested cloud.
+        name: String,
+        /// Clouds present in the configuration.
+        available: Vec<String>,
+    },
+    /// No vendor profile with this name.
+    ProfileNotFound(String),
+    /// Authentication type is not supported.
+    UnsupportedAuthType(String),
+    /// A required parameter is missing.
+    MissingParameter(String),
+    /// Two parameters cannot be used together.
+    ConflictingParameters(String, String),
+    /// A parameter has an invalid value.
+    InvalidValue {
+        /// Parameter name.
+        parameter: String,
+        /// Invalid value.
+        value: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AuthMethod {
+    Password,
+    Token,
+    ApplicationCredential,
+    Totp,
+}
+
+#[derive(Debug, Deserialize)]
+struct CloudConfigRepr {
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    auth_type: Option<String>,
+    #[serde(default)]
+    auth: AuthConfig,
+    #[serde(default)]
+    region_name: Option<String>,
+    #[serde(default)]
+    interface: Option<Interface>,
+    #[serde(default)]
+    verify: Option<bool>,
+    #[serde(default)]
+    cacert: Option<String>,
+}
+
+const API_VERSION_SUFFIX: &str = "_api_version";
+const ENDPOINT_OVERRIDE_SUFFIX: &str = "_endpoint_override";
+
+impl AuthConfig {
+    /// Build an authentication object for the given authentication type.
+    ///
+    /// The supported authentication types are `password`, `token`,
+    /// `v3applicationcredential`, `v3totp` and `v3multifactor` (with `auth_methods`), with or
+    /// without the `v3` prefix. `password` is used if no type is provided.
+    pub fn to_auth(&self, auth_type: Option<&str>) -> Result<Auth, ConfigError> {
+        let auth_type = auth_type.unwrap_or("password");
+        let methods = if auth_type == "v3multifactor" || auth_type == "multifactor" {
+            if self.auth_methods.is_empty() {
+                return Err(ConfigError::MissingParameter("auth_methods".to_string()));
+            }
+            self.auth_methods
+                .iter()
+                .map(|method| parse_auth_type(method))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![parse_auth_type(auth_type)?]
+        };
+
+        let mut identities = methods
+            .iter()
+            .map(|method| self.identity(*method))
+            .collect::<Result<Vec<_>, _>>()?;
+        let identity = if identities.len() == 1 {
+            identities.remove(0)
+        } else {
+            Identity::Multiple(identities)
+        };
+
+        let scope = self.scope()?;
+        if methods.contains(&AuthMethod::ApplicationCredential) {
+            if let Some((parameter, _)) = scope {
+                let credential = if self.application_credential_id.is_some() {
+                    "application_credential_id"
+                } else {
+                    "application_credential_name"
+                };
+                return Err(ConfigError::ConflictingParameters(
+                    credential.to_string(),
+                    parameter.to_string(),
+                ));
+            }
+        }
+
+        Ok(Auth {
+            identity,
+            scope: scope.map(|(_, scope)| scope),
+        })
+    }
+
+    fn identity(&self, method: AuthMethod) -> Result<Identity, ConfigError> {
+        Ok(match method {
+            AuthMethod::Password => {
+                let (user, domain) = self.user()?;
+                Identity::Password(UserAndPassword {
+                    user,
+                    password: required(&self.password, "password")?,
+                    domain,
+                })
+            }
+            AuthMethod::Token => Identity::Token(required(&self.token, "token")?),
+            AuthMethod::ApplicationCredential => {
+                let secret = required(
+                    &self.application_credential_secret,
+                    "application_credential_secret",
+                )?;
+                let cred = match id_or_name(
+                    &self.application_credential_id,
+                    &self.application_credential_name,
+                ) {
+                    Some(IdOrName::Id(id)) => ApplicationCredential::from_id(id, secret),
+                    Some(IdOrName::Name(name)) => {
+                        let (user, domain) = self.user()?;
+                        ApplicationCredential::from_name(name, User { user, domain }, secret)
+                    }
+                    None => {
+                        return Err(ConfigError::MissingParameter(
+                            "application_credential_id or application_credential_name".to_string(),
+                        ))
+                    }
+                };
+                Identity::ApplicationCredential(cred)
+            }
+            AuthMethod::Totp => {
+                let (user, domain) = self.user()?;
+                Identity::Totp(UserAndPasscode {
+                    user,
+                    passcode: required(&self.passcode, "passcode")?,
+                    domain,
+                })
+            }
+        })
+    }
+
+    fn user(&self) -> Result<(IdOrName, Option<IdOrName>), ConfigError> {
+        match id_or_name(&self.user_id, &self.username) {
+            Some(IdOrName::Id(id)) => Ok((IdOrName::Id(id), None)),
+            Some(name) => {
+                let domain = id_or_name(&self.user_domain_id, &self.user_domain_name)
+                    .or_else(|| self.fallback_domain());
+                Ok((name, domain))
+            }
+            None => Err(ConfigError::MissingParameter(
+                "username or user_id".to_string(),
+            )),
+        }
+    }
+
+    /// The domain of users and projects when it is not set explicitly.
+    fn fallback_domain(&self) -> Option<IdOrName> {
+        let project_scoped = self.project_id.is_some() || self.project_name.is_some();
+        let domain = if project_scoped {
+            id_or_name(&self.domain_id, &self.domain_name)
+        } else {
+            None
+        };
+        domain.or_else(|| self.default_domain.clone().map(IdOrName::Id))
+    }
+
+    fn scope(&self) -> Result<Option<(&'static str, Scope)>, ConfigError> {
+        let mut scopes = Vec::new();
+        match id_or_name(&self.project_id, &self.project_name) {
+            Some(IdOrName::Id(id)) => scopes.push((
+                "project_id",
+                Scope::Project(Project {
+                    project: IdOrName::Id(id),
+                    domain: None,
+                }),
+            )),
+            Some(name) => scopes.push((
+                "project_name",
+                Scope::Project(Project {
+                    project: name,
+                    domain: id_or_name(&self.project_domain_id, &self.project_domain_name)
+                        .or_else(|| self.fallback_domain()),
+                }),
+            )),
+            None => match id_or_name(&self.domain_id, &self.domain_name) {
+                Some(IdOrName::Id(id)) => {
+                    scopes.push(("domain_id", Scope::Domain(IdOrName::Id(id))))
+                }
+                Some(name) => scopes.push(("domain_name", Scope::Domain(name))),
+                None => (),
+            },
+        }
+        if let Some(ref system) = self.system_scope {
+            if system != "all" {
+                return Err(ConfigError::InvalidValue {
+                    parameter: "system_scope".to_string(),
+                    value: system.clone(),
+                });
+            }
+            scopes.push(("system_scope", Scope::System));
+        }
+        if let Some(ref trust_id) = self.trust_id {
+            scopes.push((
+                "trust_id",
+                Scope::Trust(IdRef {
+                    id: trust_id.clone(),
+                }),
+            ));
+        }
+
+        let mut iter = scopes.into_iter();
+        let result = iter.next();
+        if let (Some((first, _)), Some((second, _))) = (&result, iter.next()) {
+            Err(ConfigError::ConflictingParameters(
+                first.to_string(),
+                second.to_string(),
+            ))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| REDACTED);
+        f.debug_struct("AuthConfig")
+            .field("auth_url", &self.auth_url)
+            .field("username", &self.username)
+            .field("user_id", &self.user_id)
+            .field("password", &redacted(&self.password))
+            .field("token", &redacted(&self.token))
+            .field("passcode", &redacted(&self.passcode))
+            .field("project_name", &self.project_name)
+            .field("project_id", &self.project_id)
+            .field("project_domain_name", &self.project_domain_name)
+            .field("project_domain_id", &self.project_domain_id)
+            .field("user_domain_name", &self.user_domain_name)
+            .field("user_domain_id", &self.user_domain_id)
+            .field("domain_name", &self.domain_name)
+            .field("domain_id", &self.domain_id)
+            .field("default_domain", &self.default_domain)
+            .field("application_credential_id", &self.application_credential_id)
+            .field(
+                "application_credential_name",
+                &self.application_credential_name,
+            )
+            .field(
+                "application_credential_secret",
+                &redacted(&self.application_credential_secret),
+            )
+            .field("system_scope", &self.system_scope)
+            .field("trust_id", &self.trust_id)
+            .field("auth_methods", &self.auth_methods)
+            .finish()
+    }
+}
+
+impl CloudConfig {
+    /// Parse a cloud entry (the value under `clouds.<name>`).
+    pub fn from_mapping<S: Into<String>>(
+        name: S,
+        value: &Mapping,
+    ) -> Result<CloudConfig, ConfigError> {
+        let repr: CloudConfigRepr = serde_yaml::from_value(Value::Mapping(value.clone()))
+            .map_err(|err| ConfigError::InvalidFormat(err.to_string()))?;
+
+        let mut api_versions = HashMap::new();
+        let mut endpoint_overrides = HashMap::new();
+        for (key, item) in value {
+            let key = match key.as_str() {
+                Some(key) => key,
+                None => continue,
+            };
+            if let Some(service_type) = key.strip_suffix(API_VERSION_SUFFIX) {
+                let _ =
+                    api_versions.insert(service_types::normalize(service_type), scalar(key, item)?);
+            } else if let Some(service_type) = key.strip_suffix(ENDPOINT_OVERRIDE_SUFFIX) {
+                let _ = endpoint_overrides
+                    .insert(service_types::normalize(service_type), scalar(key, item)?);
+            }
+        }
+
+        Ok(CloudConfig {
+            name: name.into(),
+            profile: repr.profile,
+            auth_type: repr.auth_type,
+            auth: repr.auth,
+            region_name: repr.region_name,
+            interface: repr.interface,
+            verify: repr.verify,
+            cacert: repr.cacert,
+            api_versions,
+            endpoint_overrides,
+        })
+    }
+
+    /// Build an authentication object for this cloud.
+    #[inline]
+    pub fn to_auth(&self) -> Result<Auth, ConfigError> {
+        self.auth.to_auth(self.auth_type.as_deref())
+    }
+
+    /// An endpoint filter for the service type using the region and interface of this cloud.
+    pub fn endpoint_filter<S: Into<String>>(&self, service_type: S) -> EndpointFilter {
+        let mut result = EndpointFilter::new(service_type);
+        result.region = self.region_name.clone();
+        if let Some(ref interface) = self.interface {
+            result.interfaces = vec![interface.clone()];
+        }
+        result
+    }
+
+    /// API version requested for the service type.
+    ///
+    /// The official type and the aliases of the service type are also tried.
+    #[inline]
+    pub fn api_version(&self, service_type: &str) -> Option<&str> {
+        find_by_service_type(&self.api_versions, service_type)
+    }
+
+    /// Endpoint override for the service type.
+    ///
+    /// The official type and the aliases of the service type are also tried.
+    #[inline]
+    pub fn endpoint_override(&self, service_type: &str) -> Option<&str> {
+        find_by_service_type(&self.endpoint_overrides, service_type)
+    }
+}
+
+impl CloudsYaml {
+    /// Parse the contents of `clouds.yaml`.
+    pub fn parse(clouds: &str) -> Result<CloudsYaml, ConfigError> {
+        let mut result = CloudsYaml::default();
+        result.merge(clouds)?;
+        Ok(result)
+    }
+
+    /// Merge the contents of `secure.yaml` (or any other file in the same format).
+    #[inline]
+    pub fn merge_secure(&mut self, secure: &str) -> Result<(), ConfigError> {
+        self.merge(secure)
+    }
+
+    /// Add vendor profiles from `clouds-public.yaml`.
+    #[inline]
+    pub fn add_public_clouds(&mut self, public_clouds: &str) -> Result<(), ConfigError> {
+        self.merge(public_clouds)
+    }
+
+    /// Load configuration from the standard locations.
+    ///
+    /// Each of `clouds.yaml`, `secure.yaml` and `clouds-public.yaml` is looked for in the
+    /// current directory, `$XDG_CONFIG_HOME/openstack` (`~/.config/openstack` by default)
+    /// and `/etc/openstack`, the first file found is used. `OS_CLIENT_CONFIG_FILE` overrides
+    /// the location of `clouds.yaml`.
+    pub fn load() -> Result<CloudsYaml, ConfigError> {
+        let clouds = match env::var_os("OS_CLIENT_CONFIG_FILE") {
+            Some(path) => PathBuf::from(path),
+            None => find_file("clouds.yaml").ok_or_else(|| ConfigError::FileError {
+                path: "clouds.yaml".to_string(),
+                message: "not found in any of the standard locations".to_string(),
+            })?,
+        };
+        let mut result = CloudsYaml::parse(&read_file(&clouds)?)?;
+        if let Some(secure) = find_file("secure.yaml") {
+            result.merge_secure(&read_file(&secure)?)?;
+        }
+        if let Some(public_clouds) = find_file("clouds-public.yaml") {
+            result.add_public_clouds(&read_file(&public_clouds)?)?;
+        }
+        Ok(result)
+    }
+
+    /// Names of all clouds (sorted).
+    pub fn names(&self) -> Vec<String> {
+        let mut result = self
+            .clouds
+            .iter()
+            .filter_map(|(key, _)| key.as_str().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+
+    /// Configuration of the cloud with the given name.
+    pub fn cloud(&self, name: &str) -> Result<CloudConfig, ConfigError> {
+        let entry = self
+            .clouds
+            .get(&Value::String(name.to_string()))
+            .ok_or_else(|| ConfigError::CloudNotFound {
+                name: name.to_string(),
+                available: self.names(),
+            })?;
+        let entry = as_mapping(entry, name)?;
+
+        let merged = match entry.get(&Value::String("profile".to_string())) {
+            Some(Value::String(profile)) => {
+                let base = self
+                    .public_clouds
+                    .get(&Value::String(profile.clone()))
+                    .ok_or_else(|| ConfigError::ProfileNotFound(profile.clone()))?;
+                let mut base = as_mapping(base, profile)?.clone();
+                merge_mapping(&mut base, entry.clone());
+                base
+            }
+            Some(..) => {
+                return Err(ConfigError::InvalidFormat(format!(
+                    "profile of cloud {} must be a string",
+                    name
+                )))
+            }
+            None => entry.clone(),
+        };
+
+        CloudConfig::from_mapping(name, &merged)
+    }
+
+    fn merge(&mut self, text: &str) -> Result<(), ConfigError> {
+        let value: Value = serde_yaml::from_str(text)
+            .map_err(|err| ConfigError::InvalidFormat(err.to_string()))?;
+        let mut root = match value {
+            Value::Mapping(root) => root,
+            Value::Null => return Ok(()),
+            _ => {
+                return Err(ConfigError::InvalidFormat(
+                    "expected a mapping at the top level".to_string(),
+                ))
+            }
+        };
+        if let Some(clouds) = root.remove(&Value::String("clouds".to_string())) {
+            merge_mapping(&mut self.clouds, as_mapping(&clouds, "clouds")?.clone());
+        }
+        if let Some(public_clouds) = root.remove(&Value::String("public-clouds".to_string())) {
+            merge_mapping(
+                &mut self.public_clouds,
+                as_mapping(&public_clouds, "public-clouds")?.clone(),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::InvalidFormat(msg) => write!(f, "Invalid cloud configuration: {}", msg),
+            ConfigError::FileError { path, message } => {
+                write!(f, "Cannot read {}: {}", path, message)
+            }
+            ConfigError::CloudNotFound { name, available } => write!(
+                f,
+                "Cloud {} not found, available clouds: {}",
+                name,
+                available.join(", ")
+            ),
+            ConfigError::ProfileNotFound(name) => write!(f, "Profile {} not found", name),
+            ConfigError::UnsupportedAuthType(name) => {
+                write!(f, "Unsupported authentication type {}", name)
+            }
+            ConfigError::MissingParameter(name) => {
+                write!(f, "Missing required parameter {}", name)
+            }
+            ConfigError::ConflictingParameters(first, second) => {
+                write!(
+                    f,
+                    "Parameters {} and {} cannot be used together",
+                    first, second
+                )
+            }
+            ConfigError::InvalidValue { parameter, value } => {
+                write!(f, "Invalid value {} for parameter {}", value, parameter)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+fn parse_auth_type(auth_type: &str) -> Result<AuthMethod, ConfigError> {
+    let name = auth_type.strip_prefix("v3").unwrap_or(auth_type);
+    match name.replace('_', "").as_str() {
+        "password" => Ok(AuthMethod::Password),
+        "token" => Ok(AuthMethod::Token),
+        "applicationcredential" => Ok(AuthMethod::ApplicationCredential),
+        "totp" => Ok(AuthMethod::Totp),
+        _ => Err(ConfigError::UnsupportedAuthType(auth_type.to_string())),
+    }
+}
+
+fn id_or_name(id: &Option<String>, name: &Option<String>) -> Option<IdOrName> {
+    match (id, name) {
+        (Some(id), _) => Some(IdOrName::Id(id.clone())),
+        (None, Some(name)) => Some(IdOrName::Name(name.clone())),
+        (None, None) => None,
+    }
+}
+
+fn required(value: &Option<String>, name: &str) -> Result<String, ConfigError> {
+    value
+        .clone()
+        .ok_or_else(|| ConfigError::MissingParameter(name.to_string()))
+}
+
+fn find_by_service_type<'a>(
+    values: &'a HashMap<String, String>,
+    service_type: &str,
+) -> Option<&'a str> {
+    service_types::candidates(service_type)
+        .iter()
+        .find_map(|candidate| values.get(candidate))
+        .map(String::as_str)
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String, ConfigError> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        // Floats lose trailing zeros (2.10 becomes 2.1), which breaks microversions.
+        Value::Number(n) if n.is_f64() => Err(ConfigError::InvalidFormat(format!(
+            "{} must be quoted to avoid parsing {} as a floating point number",
+            key, n
+        ))),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(ConfigError::InvalidFormat(format!(
+            "{} must be a string or a number",
+            key
+        ))),
+    }
+}
+
+fn as_mapping<'a>(value: &'a Value, name: &str) -> Result<&'a Mapping, ConfigError> {
+    value
+        .as_mapping()
+        .ok_or_else(|| ConfigError::InvalidFormat(format!("{} must be a mapping", name)))
+}
+
+fn merge_mapping(base: &mut Mapping, overlay: Mapping) {
+    for (key, value) in overlay {
+        let merged = match (base.get_mut(&key), value) {
+            (Some(Value::Mapping(existing)), Value::Mapping(new)) => {
+                merge_mapping(existing, new);
+                None
+            }
+            (_, value) => Some(value),
+        };
+        if let Some(value) = merged {
+            let _ = base.insert(key, value);
+        }
+    }
+}
+
+fn find_file(name: &str) -> Option<PathBuf> {
+    let mut dirs = vec![PathBuf::from(".")];
+    if let Some(config_home) = env::var_os("XDG_CONFIG_HOME") {
+        dirs.push(PathBuf::from(config_home).join("openstack"));
+    } else if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".config").join("openstack"));
+    }
+    dirs.push(PathBuf::from("/etc/openstack"));
+    dirs.into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn read_file(path: &PathBuf) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|err| ConfigError::FileError {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::identity::{IdOrName, Identity, Interface, Scope};
+    use super::*;
+
+    const CLOUDS: &str = r#"
+clouds:
+  devstack:
+    auth:
+      auth_url: http://192.168.122.10/identity
+      username: demo
+      project_name: demo
+      user_domain_name: Default
+      project_domain_id: default
+    region_name: RegionOne
+    interface: internal
+    identity_api_version: 3
+    compute_api_version: '2.79'
+    volume_api_version: 3
+    block_storage_endpoint_override: http://192.168.122.10/volume/v3
+  devstack-admin:
+    auth:
+      auth_url: http://192.168.122.10/identity
+      username: admin
+      domain_name: Default
+      project_name: admin
+  devstack-system:
+    auth:
+      auth_url: http://192.168.122.10/identity
+      user_id: 0f1c6ffa0e2e4d2b8e3f2a6f4e0d9c1b
+      system_scope: all
+  ci:
+    auth_type: v3applicationcredential
+    auth:
+      auth_url: https://identity.example.com/v3
+      application_credential_id: 423f19a4ac1e4f48bbb4180756e6eb6c
+  vendor:
+    profile: example
+    auth:
+      username: demo
+      project_id: 7a2b3c4d
+    region_name: RegionTwo
+  mfa:
+    auth_type: v3multifactor
+    auth:
+      auth_url: https://identity.example.com/v3
+      auth_methods:
+        - v3password
+        - v3totp
+      username: admin
+      default_domain: default
+      domain_name: Default
+"#;
+
+    const SECURE: &str = r#"
+clouds:
+  devstack:
+    auth:
+      password: secret
+  devstack-admin:
+    auth:
+      password: admin-secret
+  ci:
+    auth:
+      application_credential_secret: rEaqvJka48mpv
+  vendor:
+    auth:
+      password: vendor-secret
+  mfa:
+    auth:
+      password: mfa-secret
+      passcode: "123456"
+"#;
+
+    const PUBLIC_CLOUDS: &str = r#"
+public-clouds:
+  example:
+    auth:
+      auth_url: https://identity.example.com/v3
+      user_domain_name: Default
+    identity_api_version: 3
+    region_name: RegionOne
+    image_api_version: 2
+"#;
+
+    fn clouds() -> CloudsYaml {
+        let mut result = CloudsYaml::parse(CLOUDS).unwrap();
+        result.merge_secure(SECURE).unwrap();
+        result.add_public_clouds(PUBLIC_CLOUDS).unwrap();
+        result
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            clouds().names(),
+            vec![
+                "ci",
+                "devstack",
+                "devstack-admin",
+                "devstack-system",
+                "mfa",
+                "vendor"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_api_version_float() {
+        for version in &["2.10", "2.60"] {
+            let clouds = CloudsYaml::parse(&format!(
+                "clouds:\n  test:\n    compute_api_version: '{}'\n",
+                version
+            ))
+            .unwrap();
+            let cloud = clouds.cloud("test").unwrap();
+            assert_eq!(cloud.api_version("compute"), Some(*version));
+
+            let clouds = CloudsYaml::parse(&format!(
+                "clouds:\n  test:\n    compute_api_version: {}\n",
+                version
+            ))
+            .unwrap();
+            match clouds.cloud("test").unwrap_err() {
+                ConfigError::InvalidFormat(msg) => {
+                    assert!(msg.contains("compute_api_version"), "{}", msg)
+                }
+                other => panic!("unexpected error {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_cloud_password() {
+        let cloud = clouds().cloud("devstack").unwrap();
+        assert_eq!(cloud.name, "devstack");
+        assert_eq!(
+            cloud.auth.auth_url.as_ref().unwrap(),
+            "http://192.168.122.10/identity"
+        );
+        assert_eq!(cloud.region_name.as_ref().unwrap(), "RegionOne");
+        assert_eq!(cloud.interface, Some(Interface::Internal));
+        assert_eq!(cloud.api_version("identity"), Some("3"));
+        assert_eq!(cloud.api_version("compute"), Some("2.79"));
+        assert_eq!(cloud.api_version("block-storage"), Some("3"));
+        assert_eq!(cloud.api_version("volume"), Some("3"));
+        assert_eq!(cloud.api_version("image"), None);
+        assert_eq!(
+            cloud.endpoint_override("volumev3"),
+            Some("http://192.168.122.10/volume/v3")
+        );
+        assert_eq!(
+            cloud.endpoint_override("block-storage"),
+            Some("http://192.168.122.10/volume/v3")
+        );
+        assert_eq!(
+            cloud.endpoint_override("block_storage"),
+            Some("http://192.168.122.10/volume/v3")
+        );
+
+        let auth = cloud.to_auth().unwrap();
+        match auth.identity {
+            Identity::Password(ref user) => {
+                assert_eq!(user.user, IdOrName::from_name("demo"));
+                assert_eq!(user.password, "secret");
+                assert_eq!(user.domain, Some(IdOrName::from_name("Default")));
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+        match auth.scope {
+            Some(Scope::Project(ref project)) => {
+                assert_eq!(project.project, IdOrName::from_name("demo"));
+                assert_eq!(project.domain, Some(IdOrName::from_id("default")));
+            }
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+
+        let filter = cloud.endpoint_filter("compute");
+        assert_eq!(filter.region.as_ref().unwrap(), "RegionOne");
+        assert_eq!(filter.interfaces, vec![Interface::Internal]);
+    }
+
+    #[test]
+    fn test_cloud_domain_name_as_default() {
+        let auth = clouds().cloud("devstack-admin").unwrap().to_auth().unwrap();
+        match auth.identity {
+            Identity::Password(ref user) => {
+                assert_eq!(user.domain, Some(IdOrName::from_name("Default")));
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+        match auth.scope {
+            Some(Scope::Project(ref project)) => {
+                assert_eq!(project.domain, Some(IdOrName::from_name("Default")));
+            }
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cloud_system_scope_missing_password() {
+        let cloud = clouds().cloud("devstack-system").unwrap();
+        assert_eq!(
+            cloud.to_auth().unwrap_err(),
+            ConfigError::MissingParameter("password".to_string())
+        );
+
+        let mut auth = cloud.auth.clone();
+        auth.password = Some("secret".to_string());
+        let result = auth.to_auth(None).unwrap();
+        assert!(matches!(result.scope, Some(Scope::System)));
+        match result.identity {
+            Identity::Password(ref user) => {
+                assert_eq!(
+                    user.user,
+                    IdOrName::from_id("0f1c6ffa0e2e4d2b8e3f2a6f4e0d9c1b")
+                );
+                assert!(user.domain.is_none());
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cloud_application_credential() {
+        let auth = clouds().cloud("ci").unwrap().to_auth().unwrap();
+        assert!(auth.scope.is_none());
+        match auth.identity {
+            Identity::ApplicationCredential(ref cred) => {
+                assert_eq!(
+                    cred.application_credential,
+                    IdOrName::from_id("423f19a4ac1e4f48bbb4180756e6eb6c")
+                );
+                assert_eq!(cred.secret, "rEaqvJka48mpv");
+                assert!(cred.user.is_none());
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cloud_profile() {
+        let cloud = clouds().cloud("vendor").unwrap();
+        assert_eq!(cloud.profile.as_ref().unwrap(), "example");
+        assert_eq!(
+            cloud.auth.auth_url.as_ref().unwrap(),
+            "https://identity.example.com/v3"
+        );
+        assert_eq!(cloud.region_name.as_ref().unwrap(), "RegionTwo");
+        assert_eq!(cloud.api_version("image"), Some("2"));
+        let auth = cloud.to_auth().unwrap();
+        match auth.identity {
+            Identity::Password(ref user) => {
+                assert_eq!(user.domain, Some(IdOrName::from_name("Default")));
+                assert_eq!(user.password, "vendor-secret");
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+        match auth.scope {
+            Some(Scope::Project(ref project)) => {
+                assert_eq!(project.project, IdOrName::from_id("7a2b3c4d"));
+            }
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cloud_multifactor() {
+        let auth = clouds().cloud("mfa").unwrap().to_auth().unwrap();
+        assert_eq!(auth.identity.methods(), vec!["password", "totp"]);
+        match auth.scope {
+            Some(Scope::Domain(ref domain)) => {
+                assert_eq!(*domain, IdOrName::from_name("Default"));
+            }
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+        match auth.identity {
+            Identity::Multiple(ref items) => match items[1] {
+                Identity::Totp(ref user) => {
+                    assert_eq!(user.passcode, "123456");
+                    assert_eq!(user.domain, Some(IdOrName::from_id("default")));
+                }
+                ref other => panic!("Unexpected identity {:?}", other),
+            },
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cloud_not_found() {
+        match clouds().cloud("nonexistent") {
+            Err(ConfigError::CloudNotFound { name, available }) => {
+                assert_eq!(name, "nonexistent");
+                assert_eq!(available.len(), 6);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_profile_not_found() {
+        let clouds = CloudsYaml::parse(CLOUDS).unwrap();
+        assert_eq!(
+            clouds.cloud("vendor").unwrap_err(),
+            ConfigError::ProfileNotFound("example".to_string())
+        );
+    }
+
+    #[test]
+    fn test_conflicting_scope() {
+        let auth = AuthConfig {
+            user_id: Some("0f1c6ffa".to_string()),
+            password: Some("secret".to_string()),
+            project_id: Some("7a2b3c4d".to_string()),
+            system_scope: Some("all".to_string()),
+            ..AuthConfig::default()
+        };
+        assert_eq!(
+            auth.to_auth(None).unwrap_err(),
+            ConfigError::ConflictingParameters(
+                "project_id".to_string(),
+                "system_scope".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_application_credential_with_scope() {
+        let auth = AuthConfig {
+            application_credential_id: Some("423f19a4".to_string()),
+            application_credential_secret: Some("secret".to_string()),
+            project_name: Some("demo".to_string()),
+            ..AuthConfig::default()
+        };
+        assert_eq!(
+            auth.to_auth(Some("v3applicationcredential")).unwrap_err(),
+            ConfigError::ConflictingParameters(
+                "application_credential_id".to_string(),
+                "project_name".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unsupported_auth_type() {
+        assert_eq!(
+            AuthConfig::default().to_auth(Some("v3oauth1")).unwrap_err(),
+            ConfigError::UnsupportedAuthType("v3oauth1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        assert!(matches!(
+            CloudsYaml::parse("clouds: [1, 2]"),
+            Err(ConfigError::InvalidFormat(..))
+        ));
+        assert!(matches!(
+            CloudsYaml::parse("clouds: {"),
+            Err(ConfigError::InvalidFormat(..))
+        ));
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let cloud = clouds().cloud("devstack").unwrap();
+        let s = format!("{:?}", cloud);
+        assert!(!s.contains("\"secret\""));
+        assert!(s.contains("demo"));
+    }
+}
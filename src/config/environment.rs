@@ -0,0 +1,699 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cloud configuration from `OS_*` environment variables and openrc files.
+
+use std::collections::HashMap;
+use std::env;
+
+use serde_yaml::{Mapping, Value};
+
+use super::{CloudConfig, CloudsYaml, ConfigError, API_VERSION_SUFFIX, ENDPOINT_OVERRIDE_SUFFIX};
+
+const PREFIX: &str = "OS_";
+
+/// Name of the cloud built from environment variables.
+const ENV_CLOUD_NAME: &str = "envvars";
+
+/// Keys of the `auth` section (variable names without the prefix in lower case).
+const AUTH_KEYS: &[&str] = &[
+    "auth_url",
+    "username",
+    "user_id",
+    "password",
+    "token",
+    "passcode",
+    "project_name",
+    "project_id",
+    "project_domain_name",
+    "project_domain_id",
+    "user_domain_name",
+    "user_domain_id",
+    "domain_name",
+    "domain_id",
+    "default_domain",
+    "application_credential_id",
+    "application_credential_name",
+    "application_credential_secret",
+    "system_scope",
+    "trust_id",
+];
+
+/// Keys of the cloud entry (variable names without the prefix in lower case).
+const CLOUD_KEYS: &[&str] = &["auth_type", "region_name", "interface", "cacert"];
+
+impl CloudConfig {
+    /// Load configuration from `OS_*` variables of the current process.
+    ///
+    /// If `OS_CLOUD` is set, the cloud is loaded with `CloudsYaml::load`.
+    pub fn from_env() -> Result<CloudConfig, ConfigError> {
+        let vars = env::vars()
+            .filter(|(name, _)| name.starts_with(PREFIX))
+            .collect::<Vec<_>>();
+        if vars
+            .iter()
+            .any(|(name, value)| name == "OS_CLOUD" && !value.is_empty())
+        {
+            let clouds = CloudsYaml::load()?;
+            CloudConfig::from_vars(vars, Some(&clouds))
+        } else {
+            CloudConfig::from_vars(vars, None)
+        }
+    }
+
+    /// Load configuration from the text of an openrc file.
+    ///
+    /// Only variables set in the file are used, see `parse_openrc` for the supported syntax.
+    /// Use `parse_openrc` with `from_vars` to add variables (e.g. a password entered
+    /// interactively).
+    pub fn from_openrc(text: &str) -> Result<CloudConfig, ConfigError> {
+        CloudConfig::from_vars(parse_openrc(text)?, None)
+    }
+
+    /// Load configuration from `OS_*` variables given as name-value pairs.
+    ///
+    /// Later values override earlier ones, empty values are ignored. The legacy
+    /// `OS_TENANT_NAME`, `OS_TENANT_ID` and `OS_ENDPOINT_TYPE` are accepted. If `OS_CLOUD` is
+    /// set, the cloud is taken from `clouds` with only `OS_REGION_NAME` and `OS_INTERFACE`
+    /// allowed to override it.
+    ///
+    /// The authentication parameters are validated, errors refer to the variable names.
+    pub fn from_vars<I, K, V>(
+        vars: I,
+        clouds: Option<&CloudsYaml>,
+    ) -> Result<CloudConfig, ConfigError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut vars = vars
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .filter(|(name, value)| name.starts_with(PREFIX) && !value.is_empty())
+            .collect::<HashMap<_, _>>();
+        merge_legacy(&mut vars, "OS_TENANT_NAME", "OS_PROJECT_NAME")?;
+        merge_legacy(&mut vars, "OS_TENANT_ID", "OS_PROJECT_ID")?;
+        if let Some(endpoint_type) = vars.get_mut("OS_ENDPOINT_TYPE") {
+            if let Some(interface) = endpoint_type.strip_suffix("URL") {
+                *endpoint_type = interface.to_string();
+            }
+        }
+        merge_legacy(&mut vars, "OS_ENDPOINT_TYPE", "OS_INTERFACE")?;
+
+        if let Some(name) = vars.remove("OS_CLOUD") {
+            return from_cloud(&name, vars, clouds);
+        }
+
+        let mut auth = Mapping::new();
+        let mut cloud = Mapping::new();
+        for (name, value) in &vars {
+            let key = name[PREFIX.len()..].to_lowercase();
+            if AUTH_KEYS.contains(&key.as_str()) {
+                let _ = auth.insert(Value::String(key), Value::String(value.clone()));
+            } else if key == "auth_methods" {
+                let methods = value
+                    .split(',')
+                    .map(|method| Value::String(method.trim().to_string()))
+                    .collect();
+                let _ = auth.insert(Value::String(key), Value::Sequence(methods));
+            } else if key == "insecure" {
+                let verify = !parse_bool(name, value)?;
+                let _ = cloud.insert(Value::String("verify".to_string()), Value::Bool(verify));
+            } else if CLOUD_KEYS.contains(&key.as_str())
+                || key.ends_with(API_VERSION_SUFFIX)
+                || key.ends_with(ENDPOINT_OVERRIDE_SUFFIX)
+            {
+                let _ = cloud.insert(Value::String(key), Value::String(value.clone()));
+            }
+        }
+
+        if !vars.contains_key("OS_AUTH_TYPE") {
+            if let Some(auth_type) = guess_auth_type(&vars)? {
+                let _ = cloud.insert(
+                    Value::String("auth_type".to_string()),
+                    Value::String(auth_type.to_string()),
+                );
+            }
+        }
+        let _ = cloud.insert(Value::String("auth".to_string()), Value::Mapping(auth));
+
+        let result = CloudConfig::from_mapping(ENV_CLOUD_NAME, &cloud)?;
+        let _ = result.to_auth().map_err(to_env_error)?;
+        Ok(result)
+    }
+}
+
+/// Parse the text of an openrc file into variables.
+///
+/// Only simple assignments (optionally with `export`) and `unset` commands are interpreted,
+/// other commands are ignored. Values can be single- or double-quoted, `$NAME` and `${NAME}`
+/// are expanded using the variables set earlier in the same file (unknown variables are
+/// expanded to empty strings).
+pub fn parse_openrc(text: &str) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut result: Vec<(String, String)> = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(str::trim_start)
+            .unwrap_or(line);
+
+        if let Some(names) = line
+            .strip_prefix("unset")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+        {
+            for name in names.split_whitespace() {
+                result.retain(|(existing, _)| existing != name);
+            }
+            continue;
+        }
+
+        let (name, value) = match line.find('=') {
+            Some(pos) if is_identifier(&line[..pos]) => (&line[..pos], &line[pos + 1..]),
+            _ => continue,
+        };
+        let value = parse_value(value, &result)
+            .map_err(|err| ConfigError::InvalidFormat(format!("line {}: {}", idx + 1, err)))?;
+        if let Some(existing) = result.iter_mut().find(|(existing, _)| existing == name) {
+            existing.1 = value;
+        } else {
+            result.push((name.to_string(), value));
+        }
+    }
+    Ok(result)
+}
+
+fn from_cloud(
+    name: &str,
+    mut vars: HashMap<String, String>,
+    clouds: Option<&CloudsYaml>,
+) -> Result<CloudConfig, ConfigError> {
+    let mut conflicting = vars
+        .keys()
+        .filter(|var| {
+            let key = var[PREFIX.len()..].to_lowercase();
+            AUTH_KEYS.contains(&key.as_str()) || key == "auth_type" || key == "auth_methods"
+        })
+        .collect::<Vec<_>>();
+    conflicting.sort();
+    if let Some(var) = conflicting.first() {
+        return Err(ConfigError::ConflictingParameters(
+            "OS_CLOUD".to_string(),
+            (*var).clone(),
+        ));
+    }
+
+    let clouds = clouds.ok_or_else(|| ConfigError::FileError {
+        path: "clouds.yaml".to_string(),
+        message: "required when OS_CLOUD is set".to_string(),
+    })?;
+    let mut result = clouds.cloud(name)?;
+    if let Some(region) = vars.remove("OS_REGION_NAME") {
+        result.region_name = Some(region);
+    }
+    if let Some(interface) = vars.remove("OS_INTERFACE") {
+        result.interface = Some(interface.into());
+    }
+    Ok(result)
+}
+
+fn merge_legacy(
+    vars: &mut HashMap<String, String>,
+    legacy: &str,
+    current: &str,
+) -> Result<(), ConfigError> {
+    if let Some(value) = vars.remove(legacy) {
+        match vars.get(current) {
+            Some(existing) if *existing != value => {
+                return Err(ConfigError::ConflictingParameters(
+                    legacy.to_string(),
+                    current.to_string(),
+                ));
+            }
+            _ => {
+                let _ = vars.insert(current.to_string(), value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn guess_auth_type(vars: &HashMap<String, String>) -> Result<Option<&'static str>, ConfigError> {
+    let credential = [
+        "OS_APPLICATION_CREDENTIAL_ID",
+        "OS_APPLICATION_CREDENTIAL_NAME",
+        "OS_APPLICATION_CREDENTIAL_SECRET",
+    ]
+    .iter()
+    .find(|name| vars.contains_key(**name));
+    let password = vars.contains_key("OS_PASSWORD");
+    let token = vars.contains_key("OS_TOKEN");
+
+    match (credential, password, token) {
+        (Some(name), true, _) => Err(ConfigError::ConflictingParameters(
+            (*name).to_string(),
+            "OS_PASSWORD".to_string(),
+        )),
+        (Some(name), _, true) => Err(ConfigError::ConflictingParameters(
+            (*name).to_string(),
+            "OS_TOKEN".to_string(),
+        )),
+        (Some(..), false, false) => Ok(Some("v3applicationcredential")),
+        (None, true, true) => Err(ConfigError::ConflictingParameters(
+            "OS_PASSWORD".to_string(),
+            "OS_TOKEN".to_string(),
+        )),
+        (None, false, true) => Ok(Some("v3token")),
+        (None, _, false) => Ok(None),
+    }
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(ConfigError::InvalidValue {
+            parameter: name.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Convert parameter names in an error to variable names.
+fn to_env_error(err: ConfigError) -> ConfigError {
+    match err {
+        ConfigError::MissingParameter(name) => ConfigError::MissingParameter(env_name(&name)),
+        ConfigError::ConflictingParameters(first, second) => {
+            ConfigError::ConflictingParameters(env_name(&first), env_name(&second))
+        }
+        ConfigError::InvalidValue { parameter, value } => ConfigError::InvalidValue {
+            parameter: env_name(&parameter),
+            value,
+        },
+        other => other,
+    }
+}
+
+fn env_name(parameter: &str) -> String {
+    parameter
+        .split(" or ")
+        .map(|name| format!("{}{}", PREFIX, name.to_uppercase()))
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn parse_value(value: &str, vars: &[(String, String)]) -> Result<String, String> {
+    let mut result = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(c) => result.push(c),
+                    None => return Err("unterminated single quote".to_string()),
+                }
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c) if "\"\\$`".contains(c) => result.push(c),
+                        Some(c) => {
+                            result.push('\\');
+                            result.push(c);
+                        }
+                        None => return Err("unterminated double quote".to_string()),
+                    },
+                    Some('$') => result.push_str(&expand(&mut chars, vars)),
+                    Some(c) => result.push(c),
+                    None => return Err("unterminated double quote".to_string()),
+                }
+            },
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    result.push(c);
+                }
+            }
+            '$' => result.push_str(&expand(&mut chars, vars)),
+            c if c.is_whitespace() || c == ';' => break,
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+fn expand<I>(chars: &mut std::iter::Peekable<I>, vars: &[(String, String)]) -> String
+where
+    I: Iterator<Item = char>,
+{
+    let mut name = String::new();
+    if chars.peek() == Some(&'{') {
+        let _ = chars.next();
+        for c in chars.by_ref() {
+            if c == '}' {
+                break;
+            }
+            name.push(c);
+        }
+    } else {
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                let _ = chars.next();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            return "$".to_string();
+        }
+    }
+    vars.iter()
+        .find(|(existing, _)| *existing == name)
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::identity::{IdOrName, Identity, Interface, Scope};
+    use super::super::{CloudConfig, CloudsYaml, ConfigError};
+    use super::parse_openrc;
+
+    const OPENRC: &str = r#"#!/usr/bin/env bash
+# To use an OpenStack cloud you need to authenticate against the Identity
+# service named keystone, which returns a **Token** and **Service Catalog**.
+export OS_AUTH_URL=http://192.168.122.10/identity
+export OS_PROJECT_ID=a6944d763bf64ee6a275f1263fae0352
+export OS_PROJECT_NAME="admin"
+export OS_USER_DOMAIN_NAME="Default"
+if [ -z "$OS_USER_DOMAIN_NAME" ]; then unset OS_USER_DOMAIN_NAME; fi
+export OS_PROJECT_DOMAIN_ID="default"
+if [ -z "$OS_PROJECT_DOMAIN_ID" ]; then unset OS_PROJECT_DOMAIN_ID; fi
+# unset v2.0 items in case set
+unset OS_TENANT_ID
+unset OS_TENANT_NAME
+export OS_USERNAME="admin"
+echo "Please enter your OpenStack Password for project $OS_PROJECT_NAME as user $OS_USERNAME: "
+read -sr OS_PASSWORD_INPUT
+export OS_PASSWORD=$OS_PASSWORD_INPUT
+export OS_REGION_NAME="RegionOne"
+if [ -z "$OS_REGION_NAME" ]; then unset OS_REGION_NAME; fi
+export OS_INTERFACE=public
+export OS_IDENTITY_API_VERSION=3
+"#;
+
+    #[test]
+    fn test_parse_openrc() {
+        let vars = parse_openrc(OPENRC).unwrap();
+        let get = |name: &str| {
+            vars.iter()
+                .find(|(existing, _)| existing == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(get("OS_PROJECT_NAME"), Some("admin"));
+        assert_eq!(get("OS_USER_DOMAIN_NAME"), Some("Default"));
+        assert_eq!(get("OS_PASSWORD"), Some(""));
+        assert_eq!(get("OS_IDENTITY_API_VERSION"), Some("3"));
+        assert_eq!(get("OS_TENANT_ID"), None);
+        assert_eq!(vars[0].0, "OS_AUTH_URL");
+    }
+
+    #[test]
+    fn test_parse_openrc_quoting() {
+        let vars = parse_openrc(
+            r#"
+OS_A='single $quoted' # comment
+export OS_B="double \"$OS_A\""
+export OS_C=${OS_B}-suffix; echo done
+export OS_D=plain
+unset OS_D
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("OS_A".to_string(), "single $quoted".to_string()),
+                ("OS_B".to_string(), "double \"single $quoted\"".to_string()),
+                (
+                    "OS_C".to_string(),
+                    "double \"single $quoted\"-suffix".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_openrc_unterminated() {
+        assert!(matches!(
+            parse_openrc("export OS_PASSWORD=\"secret"),
+            Err(ConfigError::InvalidFormat(..))
+        ));
+    }
+
+    #[test]
+    fn test_from_openrc_missing_password() {
+        assert_eq!(
+            CloudConfig::from_openrc(OPENRC).unwrap_err(),
+            ConfigError::MissingParameter("OS_PASSWORD".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_openrc_with_password() {
+        let mut vars = parse_openrc(OPENRC).unwrap();
+        vars.push(("OS_PASSWORD".to_string(), "devstacker".to_string()));
+        let cloud = CloudConfig::from_vars(vars, None).unwrap();
+        assert_eq!(cloud.region_name.as_ref().unwrap(), "RegionOne");
+        assert_eq!(cloud.interface, Some(Interface::Public));
+        assert_eq!(cloud.api_version("identity"), Some("3"));
+
+        let auth = cloud.to_auth().unwrap();
+        match auth.identity {
+            Identity::Password(ref user) => {
+                assert_eq!(user.user, IdOrName::from_name("admin"));
+                assert_eq!(user.password, "devstacker");
+                assert_eq!(user.domain, Some(IdOrName::from_name("Default")));
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+        match auth.scope {
+            Some(Scope::Project(ref project)) => {
+                assert_eq!(
+                    project.project,
+                    IdOrName::from_id("a6944d763bf64ee6a275f1263fae0352")
+                );
+            }
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_vars_application_credential() {
+        let cloud = CloudConfig::from_vars(
+            vec![
+                ("OS_AUTH_URL", "https://identity.example.com/v3"),
+                (
+                    "OS_APPLICATION_CREDENTIAL_ID",
+                    "423f19a4ac1e4f48bbb4180756e6eb6c",
+                ),
+                ("OS_APPLICATION_CREDENTIAL_SECRET", "rEaqvJka48mpv"),
+                ("OS_ENDPOINT_TYPE", "internalURL"),
+                ("OS_INSECURE", "true"),
+                ("HOME", "/root"),
+            ],
+            None,
+        )
+        .unwrap();
+        assert_eq!(cloud.auth_type.as_ref().unwrap(), "v3applicationcredential");
+        assert_eq!(cloud.interface, Some(Interface::Internal));
+        assert_eq!(cloud.verify, Some(false));
+        match cloud.to_auth().unwrap().identity {
+            Identity::ApplicationCredential(ref cred) => {
+                assert_eq!(cred.secret, "rEaqvJka48mpv");
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_vars_token_domain_scope() {
+        let cloud = CloudConfig::from_vars(
+            vec![
+                ("OS_AUTH_URL", "https://identity.example.com/v3"),
+                ("OS_TOKEN", "abcdef"),
+                ("OS_DOMAIN_NAME", "Default"),
+            ],
+            None,
+        )
+        .unwrap();
+        let auth = cloud.to_auth().unwrap();
+        assert_eq!(auth.identity.methods(), vec!["token"]);
+        match auth.scope {
+            Some(Scope::Domain(ref domain)) => assert_eq!(*domain, IdOrName::from_name("Default")),
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_vars_tenant_name() {
+        let cloud = CloudConfig::from_vars(
+            vec![
+                ("OS_USERNAME", "demo"),
+                ("OS_PASSWORD", "secret"),
+                ("OS_TENANT_NAME", "demo"),
+                ("OS_PROJECT_NAME", ""),
+            ],
+            None,
+        )
+        .unwrap();
+        assert_eq!(cloud.auth.project_name.as_ref().unwrap(), "demo");
+
+        assert_eq!(
+            CloudConfig::from_vars(
+                vec![
+                    ("OS_USERNAME", "demo"),
+                    ("OS_PASSWORD", "secret"),
+                    ("OS_TENANT_NAME", "demo"),
+                    ("OS_PROJECT_NAME", "admin"),
+                ],
+                None,
+            )
+            .unwrap_err(),
+            ConfigError::ConflictingParameters(
+                "OS_TENANT_NAME".to_string(),
+                "OS_PROJECT_NAME".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_vars_conflicts() {
+        assert_eq!(
+            CloudConfig::from_vars(
+                vec![
+                    ("OS_USERNAME", "demo"),
+                    ("OS_PASSWORD", "secret"),
+                    ("OS_APPLICATION_CREDENTIAL_ID", "423f19a4"),
+                ],
+                None,
+            )
+            .unwrap_err(),
+            ConfigError::ConflictingParameters(
+                "OS_APPLICATION_CREDENTIAL_ID".to_string(),
+                "OS_PASSWORD".to_string()
+            )
+        );
+        assert_eq!(
+            CloudConfig::from_vars(
+                vec![
+                    ("OS_USER_ID", "0f1c6ffa"),
+                    ("OS_PASSWORD", "secret"),
+                    ("OS_PROJECT_NAME", "demo"),
+                    ("OS_SYSTEM_SCOPE", "all"),
+                ],
+                None,
+            )
+            .unwrap_err(),
+            ConfigError::ConflictingParameters(
+                "OS_PROJECT_NAME".to_string(),
+                "OS_SYSTEM_SCOPE".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_vars_incomplete() {
+        assert_eq!(
+            CloudConfig::from_vars(vec![("OS_PASSWORD", "secret")], None).unwrap_err(),
+            ConfigError::MissingParameter("OS_USERNAME or OS_USER_ID".to_string())
+        );
+        assert_eq!(
+            CloudConfig::from_vars(vec![("OS_APPLICATION_CREDENTIAL_NAME", "monitoring")], None)
+                .unwrap_err(),
+            ConfigError::MissingParameter("OS_APPLICATION_CREDENTIAL_SECRET".to_string())
+        );
+        assert_eq!(
+            CloudConfig::from_vars(
+                vec![
+                    ("OS_USER_ID", "0f1c6ffa"),
+                    ("OS_PASSWORD", "secret"),
+                    ("OS_INSECURE", "maybe"),
+                ],
+                None
+            )
+            .unwrap_err(),
+            ConfigError::InvalidValue {
+                parameter: "OS_INSECURE".to_string(),
+                value: "maybe".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_vars_cloud() {
+        let clouds = CloudsYaml::parse(
+            r#"
+clouds:
+  devstack:
+    auth:
+      auth_url: http://192.168.122.10/identity
+      user_id: 0f1c6ffa
+      password: secret
+      project_id: 7a2b3c4d
+    region_name: RegionOne
+"#,
+        )
+        .unwrap();
+
+        let cloud = CloudConfig::from_vars(
+            vec![("OS_CLOUD", "devstack"), ("OS_REGION_NAME", "RegionTwo")],
+            Some(&clouds),
+        )
+        .unwrap();
+        assert_eq!(cloud.name, "devstack");
+        assert_eq!(cloud.region_name.as_ref().unwrap(), "RegionTwo");
+
+        assert_eq!(
+            CloudConfig::from_vars(
+                vec![("OS_CLOUD", "devstack"), ("OS_PASSWORD", "other")],
+                Some(&clouds),
+            )
+            .unwrap_err(),
+            ConfigError::ConflictingParameters("OS_CLOUD".to_string(), "OS_PASSWORD".to_string())
+        );
+        assert!(matches!(
+            CloudConfig::from_vars(vec![("OS_CLOUD", "devstack")], None),
+            Err(ConfigError::FileError { .. })
+        ));
+        assert!(matches!(
+            CloudConfig::from_vars(vec![("OS_CLOUD", "other")], Some(&clouds)),
+            Err(ConfigError::CloudNotFound { .. })
+        ));
+    }
+}
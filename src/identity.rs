@@ -14,13 +14,55 @@
 
 //! Identity V3 JSON structures and protocol bits.
 
-use chrono::{DateTime, FixedOffset};
-use serde::ser::SerializeStruct;
-use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
 
-use super::common::IdAndName;
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::de::Error as DeserError;
+use serde::ser::{Error as SerError, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::common::{self, IdAndName, IdRef};
+
+pub mod application_credentials;
+mod catalog;
+pub mod credentials;
+pub mod ec2;
+pub mod endpoints;
+pub mod federation;
+pub mod limits;
+pub mod oidc;
+pub mod projects;
+pub mod revoke;
+pub mod roles;
+pub mod tokens;
+pub mod trusts;
+pub mod users;
+pub mod v2;
+
+pub use self::catalog::{CatalogError, EndpointFilter};
+
+/// Placeholder used instead of secrets in `Debug` output and redacted copies.
+pub const REDACTED: &str = "***";
+
+/// Header carrying an authentication receipt.
+pub const RECEIPT_HEADER: &str = "Openstack-Auth-Receipt";
+
+/// Default safety margin (in seconds) before token expiration for renewing it.
+pub const DEFAULT_RENEWAL_MARGIN: i64 = 30;
+
+/// A source of the current time.
+pub trait Clock {
+    /// Current date and time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A clock using the system time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
 
 /// A reference to a resource by its ID or name.
+///
+/// When deserializing, the ID takes precedence if both are present.
 #[derive(Clone, Debug, Serialize, PartialEq, Eq, Hash)]
 pub enum IdOrName {
     /// Resource ID.
@@ -32,7 +74,7 @@ pub enum IdOrName {
 }
 
 /// User and password.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct UserAndPassword {
     #[serde(flatten)]
     pub user: IdOrName,
@@ -41,17 +83,73 @@ pub struct UserAndPassword {
     pub domain: Option<IdOrName>,
 }
 
+/// User and a TOTP passcode.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct UserAndPasscode {
+    #[serde(flatten)]
+    pub user: IdOrName,
+    pub passcode: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<IdOrName>,
+}
+
+/// A reference to a user in a domain.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct User {
+    #[serde(flatten)]
+    pub user: IdOrName,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<IdOrName>,
+}
+
+/// Application credential and its secret.
+///
+/// The owning user is required when the application credential is referenced by name, use
+/// `ApplicationCredential::from_id` or `ApplicationCredential::from_name` to build valid
+/// requests.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ApplicationCredential {
+    #[serde(flatten)]
+    pub application_credential: IdOrName,
+    pub secret: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user: Option<User>,
+}
+
+/// An identity provider and a protocol for federated authentication.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Federated {
+    pub identity_provider: String,
+    pub protocol: String,
+}
+
 /// Authentication identity.
-#[derive(Clone, Debug)]
+///
+/// The `Debug` output never contains secrets, use `Identity::redacted` to get a copy that is
+/// safe to serialize into logs.
+#[derive(Clone)]
 pub enum Identity {
     /// Authentication with a user and a password.
     Password(UserAndPassword),
     /// Authentication with a token.
     Token(String),
+    /// Authentication with an application credential.
+    ApplicationCredential(ApplicationCredential),
+    /// Authentication with a user and a TOTP passcode.
+    Totp(UserAndPasscode),
+    /// Federated authentication with attributes provided by the web server (`mapped` method).
+    Mapped(Federated),
+    /// Federated authentication with OpenID Connect claims provided by the web server
+    /// (`openid` method).
+    OpenId(Federated),
+    /// Authentication with several methods at once (e.g. for multi-factor authentication).
+    ///
+    /// Nested identities are flattened, each method may only be used once.
+    Multiple(Vec<Identity>),
 }
 
 /// A reference to a project in a domain.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Project {
     #[serde(flatten)]
     pub project: IdOrName,
@@ -60,7 +158,7 @@ pub struct Project {
 }
 
 /// A scope.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Scope {
     /// Project scope.
     #[serde(rename = "project")]
@@ -68,39 +166,75 @@ pub enum Scope {
     /// Domain scope.
     #[serde(rename = "domain")]
     Domain(IdOrName),
-    #[serde(rename = "system", serialize_with = "ser_system_scope")]
+    #[serde(
+        rename = "system",
+        serialize_with = "ser_system_scope",
+        deserialize_with = "de_system_scope"
+    )]
     System,
+    /// Trust scope.
+    #[serde(rename = "OS-TRUST:trust")]
+    Trust(IdRef),
+    /// Service provider scope for Keystone-to-Keystone federation.
+    ///
+    /// Used to request a SAML assertion for the service provider, see
+    /// `federation::ServiceProvider::assertion_request`.
+    #[serde(rename = "service_provider")]
+    ServiceProvider(IdRef),
 }
 
 /// An authentication object.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Auth {
     /// Authentication identity.
     pub identity: Identity,
     /// Authentication scope (if needed).
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub scope: Option<Scope>,
 }
 
 /// An authentication request root.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AuthRoot {
     pub auth: Auth,
 }
 
+/// An endpoint interface.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Interface {
+    /// Public interface.
+    Public,
+    /// Internal interface.
+    Internal,
+    /// Admin interface.
+    Admin,
+    /// Interface unknown to this crate.
+    Other(String),
+}
+
 /// An endpoint in the catalog.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Endpoint {
-    pub interface: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    pub interface: Interface,
     pub region: String,
+    #[serde(default)]
+    pub region_id: Option<String>,
     pub url: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
 }
 
 /// A service catalog record.
 #[derive(Clone, Debug, Deserialize)]
 pub struct CatalogRecord {
+    #[serde(default)]
+    pub id: Option<String>,
     #[serde(rename = "type")]
     pub service_type: String,
+    #[serde(default)]
+    pub name: Option<String>,
     pub endpoints: Vec<Endpoint>,
 }
 
@@ -113,9 +247,84 @@ pub struct CatalogRoot {
 /// An authentication token with embedded catalog.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Token {
+    #[serde(default)]
     pub roles: Vec<IdAndName>,
     pub expires_at: DateTime<FixedOffset>,
+    pub issued_at: DateTime<FixedOffset>,
+    #[serde(default)]
     pub catalog: Vec<CatalogRecord>,
+    pub user: TokenUser,
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default)]
+    pub audit_ids: Vec<String>,
+    #[serde(default)]
+    pub project: Option<TokenProject>,
+    #[serde(default)]
+    pub is_domain: bool,
+    #[serde(default)]
+    pub domain: Option<IdAndName>,
+    #[serde(default)]
+    pub system: Option<TokenSystem>,
+    #[serde(default)]
+    pub application_credential: Option<TokenApplicationCredential>,
+    #[serde(rename = "OS-TRUST:trust", default)]
+    pub trust: Option<TokenTrust>,
+    /// Whether the project is the admin project (only if the admin project is configured).
+    #[serde(default)]
+    pub is_admin_project: Option<bool>,
+}
+
+/// A project in a token.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenProject {
+    pub id: String,
+    pub name: String,
+    pub domain: IdAndName,
+}
+
+/// A system scope in a token.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct TokenSystem {
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// An application credential used to issue a token.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenApplicationCredential {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub restricted: bool,
+    /// Access rules restricting the requests allowed with the token.
+    #[serde(default)]
+    pub access_rules: Option<Vec<application_credentials::AccessRule>>,
+}
+
+/// A trust used to issue a token.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenTrust {
+    pub id: String,
+    #[serde(default)]
+    pub impersonation: bool,
+    pub trustee_user: IdRef,
+    pub trustor_user: IdRef,
+}
+
+/// A scope of a token.
+///
+/// Trust-scoped tokens are also project-scoped, see `Token::trust` for the trust itself.
+#[derive(Clone, Copy, Debug)]
+pub enum TokenScope<'a> {
+    /// An unscoped token.
+    Unscoped,
+    /// A project-scoped token.
+    Project(&'a TokenProject),
+    /// A domain-scoped token.
+    Domain(&'a IdAndName),
+    /// A system-scoped token.
+    System(&'a TokenSystem),
 }
 
 /// A token response root.
@@ -124,16 +333,95 @@ pub struct TokenRoot {
     pub token: Token,
 }
 
-#[derive(Debug, Serialize)]
-struct PasswordAuth<'a> {
-    user: &'a UserAndPassword,
+/// A user as returned in tokens and receipts.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenUser {
+    pub id: String,
+    pub name: String,
+    pub domain: IdAndName,
+    #[serde(deserialize_with = "common::opt_datetime_as_utc", default)]
+    pub password_expires_at: Option<DateTime<FixedOffset>>,
+    /// Federation information for federated users.
+    #[serde(rename = "OS-FEDERATION", default)]
+    pub federation: Option<TokenFederation>,
+}
+
+/// Federation information of a user in a token.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenFederation {
+    pub identity_provider: IdRef,
+    pub protocol: IdRef,
+    /// Groups the user was mapped to.
+    #[serde(default)]
+    pub groups: Vec<IdRef>,
+}
+
+/// An authentication receipt for partially satisfied multi-factor rules.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Receipt {
+    pub methods: Vec<String>,
+    pub user: TokenUser,
+    pub expires_at: DateTime<FixedOffset>,
+    pub issued_at: DateTime<FixedOffset>,
+}
+
+/// A receipt response root (returned with HTTP 401).
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReceiptRoot {
+    pub receipt: Receipt,
+    /// Sets of methods, any of which satisfies the multi-factor rules.
+    pub required_auth_methods: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdOrNameRepr {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-struct TokenAuth<'a> {
-    id: &'a str,
+#[derive(Debug, Deserialize, Serialize)]
+struct PasswordAuth<T> {
+    user: T,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+struct TokenAuth<T> {
+    id: T,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TotpAuth<T> {
+    user: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentityRepr {
+    methods: Vec<String>,
+    #[serde(default)]
+    password: Option<PasswordAuth<UserAndPassword>>,
+    #[serde(default)]
+    token: Option<TokenAuth<String>>,
+    #[serde(default)]
+    application_credential: Option<ApplicationCredential>,
+    #[serde(default)]
+    totp: Option<TotpAuth<UserAndPasscode>>,
+    #[serde(default)]
+    mapped: Option<Federated>,
+    #[serde(default)]
+    openid: Option<Federated>,
+}
+
+const METHODS: &[&str] = &[
+    "password",
+    "token",
+    "application_credential",
+    "totp",
+    "mapped",
+    "openid",
+];
+
 impl IdOrName {
     /// Create an ID from anything that can be converted to a string.
     #[inline]
@@ -148,26 +436,474 @@ impl IdOrName {
     }
 }
 
-impl Serialize for Identity {
+impl<'de> Deserialize<'de> for IdOrName {
+    fn deserialize<D>(deserializer: D) -> Result<IdOrName, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = IdOrNameRepr::deserialize(deserializer)?;
+        match (repr.id, repr.name) {
+            (Some(id), _) => Ok(IdOrName::Id(id)),
+            (None, Some(name)) => Ok(IdOrName::Name(name)),
+            (None, None) => Err(D::Error::custom("either id or name is required")),
+        }
+    }
+}
+
+impl Interface {
+    /// Interface as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Interface::Public => "public",
+            Interface::Internal => "internal",
+            Interface::Admin => "admin",
+            Interface::Other(ref value) => value,
+        }
+    }
+}
+
+impl From<&str> for Interface {
+    fn from(value: &str) -> Interface {
+        match value {
+            "public" => Interface::Public,
+            "internal" => Interface::Internal,
+            "admin" => Interface::Admin,
+            other => Interface::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for Interface {
+    fn from(value: String) -> Interface {
+        match Interface::from(value.as_str()) {
+            Interface::Other(..) => Interface::Other(value),
+            known => known,
+        }
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Interface {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut inner = serializer.serialize_struct("Identity", 2)?;
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Interface {
+    fn deserialize<D>(deserializer: D) -> Result<Interface, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: String = Deserialize::deserialize(deserializer)?;
+        Ok(Interface::from(value))
+    }
+}
+
+impl UserAndPassword {
+    /// A copy with the password replaced by a placeholder.
+    pub fn redacted(&self) -> UserAndPassword {
+        UserAndPassword {
+            password: REDACTED.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for UserAndPassword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserAndPassword")
+            .field("user", &self.user)
+            .field("password", &REDACTED)
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
+impl UserAndPasscode {
+    /// A copy with the passcode replaced by a placeholder.
+    pub fn redacted(&self) -> UserAndPasscode {
+        UserAndPasscode {
+            passcode: REDACTED.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for UserAndPasscode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserAndPasscode")
+            .field("user", &self.user)
+            .field("passcode", &REDACTED)
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
+impl ApplicationCredential {
+    /// An application credential referenced by its ID.
+    pub fn from_id<S1, S2>(id: S1, secret: S2) -> ApplicationCredential
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        ApplicationCredential {
+            application_credential: IdOrName::from_id(id),
+            secret: secret.into(),
+            user: None,
+        }
+    }
+
+    /// An application credential referenced by its name and the owning user.
+    pub fn from_name<S1, S2>(name: S1, user: User, secret: S2) -> ApplicationCredential
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        ApplicationCredential {
+            application_credential: IdOrName::from_name(name),
+            secret: secret.into(),
+            user: Some(user),
+        }
+    }
+
+    /// A copy with the secret replaced by a placeholder.
+    pub fn redacted(&self) -> ApplicationCredential {
+        ApplicationCredential {
+            secret: REDACTED.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for ApplicationCredential {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApplicationCredential")
+            .field("application_credential", &self.application_credential)
+            .field("secret", &REDACTED)
+            .field("user", &self.user)
+            .finish()
+    }
+}
+
+impl Identity {
+    /// A copy with all secrets replaced by a placeholder.
+    ///
+    /// Serializing the result produces a request body that is safe to log.
+    pub fn redacted(&self) -> Identity {
+        match self {
+            Identity::Password(ref user) => Identity::Password(user.redacted()),
+            Identity::Token(..) => Identity::Token(REDACTED.to_string()),
+            Identity::ApplicationCredential(ref cred) => {
+                Identity::ApplicationCredential(cred.redacted())
+            }
+            Identity::Totp(ref user) => Identity::Totp(user.redacted()),
+            Identity::Mapped(..) | Identity::OpenId(..) => self.clone(),
+            Identity::Multiple(ref items) => {
+                Identity::Multiple(items.iter().map(Identity::redacted).collect())
+            }
+        }
+    }
+
+    /// Names of the authentication methods used by this identity.
+    pub fn methods(&self) -> Vec<&'static str> {
+        self.flatten().into_iter().map(Identity::method).collect()
+    }
+
+    fn flatten(&self) -> Vec<&Identity> {
+        if let Identity::Multiple(ref items) = self {
+            items.iter().flat_map(Identity::flatten).collect()
+        } else {
+            vec![self]
+        }
+    }
+
+    fn method(&self) -> &'static str {
         match self {
-            Identity::Password(ref user) => {
-                inner.serialize_field("methods", &["password"])?;
-                inner.serialize_field("password", &PasswordAuth { user })?;
+            Identity::Password(..) => "password",
+            Identity::Token(..) => "token",
+            Identity::ApplicationCredential(..) => "application_credential",
+            Identity::Totp(..) => "totp",
+            Identity::Mapped(..) => "mapped",
+            Identity::OpenId(..) => "openid",
+            Identity::Multiple(..) => unreachable!("flatten never returns Multiple"),
+        }
+    }
+}
+
+impl Serialize for Identity {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let identities = self.flatten();
+        let methods = self.methods();
+        if methods.is_empty() {
+            return Err(S::Error::custom(
+                "at least one authentication method is required",
+            ));
+        }
+        for (idx, method) in methods.iter().enumerate() {
+            if methods[..idx].contains(method) {
+                return Err(S::Error::custom(format!(
+                    "authentication method {} is used more than once",
+                    method
+                )));
             }
-            Identity::Token(ref token) => {
-                inner.serialize_field("methods", &["token"])?;
-                inner.serialize_field("token", &TokenAuth { id: token })?;
+        }
+
+        let mut inner = serializer.serialize_struct("Identity", identities.len() + 1)?;
+        inner.serialize_field("methods", &methods)?;
+        for identity in identities {
+            match identity {
+                Identity::Password(ref user) => {
+                    inner.serialize_field("password", &PasswordAuth { user })?;
+                }
+                Identity::Token(ref token) => {
+                    inner.serialize_field("token", &TokenAuth { id: token })?;
+                }
+                Identity::ApplicationCredential(ref cred) => {
+                    inner.serialize_field("application_credential", cred)?;
+                }
+                Identity::Totp(ref user) => {
+                    inner.serialize_field("totp", &TotpAuth { user })?;
+                }
+                Identity::Mapped(ref federated) => {
+                    inner.serialize_field("mapped", federated)?;
+                }
+                Identity::OpenId(ref federated) => {
+                    inner.serialize_field("openid", federated)?;
+                }
+                Identity::Multiple(..) => unreachable!("flatten never returns Multiple"),
             }
         }
         inner.end()
     }
 }
 
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identity::Password(ref user) => f.debug_tuple("Password").field(user).finish(),
+            Identity::Token(..) => f.debug_tuple("Token").field(&REDACTED).finish(),
+            Identity::ApplicationCredential(ref cred) => {
+                f.debug_tuple("ApplicationCredential").field(cred).finish()
+            }
+            Identity::Totp(ref user) => f.debug_tuple("Totp").field(user).finish(),
+            Identity::Mapped(ref federated) => f.debug_tuple("Mapped").field(federated).finish(),
+            Identity::OpenId(ref federated) => f.debug_tuple("OpenId").field(federated).finish(),
+            Identity::Multiple(ref items) => f.debug_tuple("Multiple").field(items).finish(),
+        }
+    }
+}
+
+impl Federated {
+    /// Create a federated identity from an identity provider and a protocol.
+    pub fn new<S1, S2>(identity_provider: S1, protocol: S2) -> Federated
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Federated {
+            identity_provider: identity_provider.into(),
+            protocol: protocol.into(),
+        }
+    }
+
+    /// Path (relative to the identity endpoint) for federated authentication.
+    ///
+    /// This endpoint is protected by the web server and issues an unscoped token. OpenID Connect
+    /// access tokens are sent to it in the `Authorization: Bearer` header.
+    pub fn auth_path(&self) -> String {
+        format!(
+            "OS-FEDERATION/identity_providers/{}/protocols/{}/auth",
+            self.identity_provider, self.protocol
+        )
+    }
+}
+
+impl Auth {
+    /// A copy with all secrets replaced by a placeholder.
+    #[inline]
+    pub fn redacted(&self) -> Auth {
+        Auth {
+            identity: self.identity.redacted(),
+            scope: self.scope.clone(),
+        }
+    }
+}
+
+impl AuthRoot {
+    /// A request to rescope an existing token.
+    ///
+    /// The new token will have the same expiration time as the original one.
+    pub fn rescope<S: Into<String>>(token: S, scope: Scope) -> AuthRoot {
+        AuthRoot {
+            auth: Auth {
+                identity: Identity::Token(token.into()),
+                scope: Some(scope),
+            },
+        }
+    }
+
+    /// A copy with all secrets replaced by a placeholder.
+    ///
+    /// Serializing the result produces a request body that is safe to log.
+    #[inline]
+    pub fn redacted(&self) -> AuthRoot {
+        AuthRoot {
+            auth: self.auth.redacted(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Identity {
+    fn deserialize<D>(deserializer: D) -> Result<Identity, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut repr = IdentityRepr::deserialize(deserializer)?;
+        let mut identities = Vec::with_capacity(repr.methods.len());
+        for method in &repr.methods {
+            let identity = match method.as_str() {
+                "password" => repr
+                    .password
+                    .take()
+                    .map(|auth| Identity::Password(auth.user)),
+                "token" => repr.token.take().map(|auth| Identity::Token(auth.id)),
+                "application_credential" => repr
+                    .application_credential
+                    .take()
+                    .map(Identity::ApplicationCredential),
+                "totp" => repr.totp.take().map(|auth| Identity::Totp(auth.user)),
+                "mapped" => repr.mapped.take().map(Identity::Mapped),
+                "openid" => repr.openid.take().map(Identity::OpenId),
+                other => return Err(D::Error::unknown_variant(other, METHODS)),
+            };
+            if let Some(identity) = identity {
+                identities.push(identity);
+            } else {
+                return Err(D::Error::custom(format!(
+                    "authentication method {} is used more than once or has no data",
+                    method
+                )));
+            }
+        }
+
+        match identities.len() {
+            0 => Err(D::Error::custom(
+                "at least one authentication method is required",
+            )),
+            1 => Ok(identities.remove(0)),
+            _ => Ok(Identity::Multiple(identities)),
+        }
+    }
+}
+
+impl Token {
+    /// Scope of the token.
+    pub fn scope(&self) -> TokenScope<'_> {
+        if let Some(ref project) = self.project {
+            TokenScope::Project(project)
+        } else if let Some(ref domain) = self.domain {
+            TokenScope::Domain(domain)
+        } else if let Some(ref system) = self.system {
+            TokenScope::System(system)
+        } else {
+            TokenScope::Unscoped
+        }
+    }
+
+    /// Total lifetime of the token.
+    #[inline]
+    pub fn lifetime(&self) -> Duration {
+        self.expires_at - self.issued_at
+    }
+
+    /// Remaining lifetime of the token (negative if it has expired).
+    pub fn remaining_lifetime<C: Clock + ?Sized>(&self, clock: &C) -> Duration {
+        self.expires_at.with_timezone(&Utc) - clock.now()
+    }
+
+    /// Whether the token has expired.
+    #[inline]
+    pub fn is_expired<C: Clock + ?Sized>(&self, clock: &C) -> bool {
+        self.remaining_lifetime(clock) <= Duration::zero()
+    }
+
+    /// Time when the token should be renewed given the safety margin.
+    ///
+    /// The margin accounts for clock skew and request latency. It is capped at half of the token
+    /// lifetime, so that short-lived tokens are not renewed right after being issued.
+    pub fn renew_at(&self, margin: Duration) -> DateTime<FixedOffset> {
+        self.expires_at - std::cmp::min(margin, self.lifetime() / 2)
+    }
+
+    /// Whether the token should be renewed given the safety margin, see `renew_at`.
+    #[inline]
+    pub fn needs_renewal<C: Clock + ?Sized>(&self, clock: &C, margin: Duration) -> bool {
+        clock.now() >= self.renew_at(margin)
+    }
+}
+
+impl TokenRoot {
+    /// Whether the token should be renewed using the default safety margin.
+    #[inline]
+    pub fn needs_renewal<C: Clock + ?Sized>(&self, clock: &C) -> bool {
+        self.token
+            .needs_renewal(clock, Duration::seconds(DEFAULT_RENEWAL_MARGIN))
+    }
+}
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A fixed time can be used as a clock, e.g. in tests.
+impl Clock for DateTime<Utc> {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        *self
+    }
+}
+
+impl ReceiptRoot {
+    /// Sets of methods that still have to be provided together with the receipt.
+    ///
+    /// Any of the returned sets is enough to satisfy the multi-factor rules.
+    pub fn missing_methods(&self) -> Vec<Vec<&str>> {
+        self.required_auth_methods
+            .iter()
+            .map(|rule| {
+                rule.iter()
+                    .filter(|method| !self.receipt.methods.contains(method))
+                    .map(String::as_str)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[inline]
+fn default_enabled() -> bool {
+    true
+}
+
+#[inline]
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 fn ser_system_scope<S>(serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -177,6 +913,20 @@ where
     inner.end()
 }
 
+fn de_system_scope<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let system = TokenSystem::deserialize(deserializer)?;
+    if system.all {
+        Ok(())
+    } else {
+        Err(D::Error::custom(
+            "only the whole system can be used as a scope",
+        ))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::super::common::test;
@@ -265,23 +1015,536 @@ mod test {
     }
 }"#;
 
-    #[test]
-    fn test_password_name_unscoped() {
-        let value = AuthRoot {
-            auth: Auth {
-                identity: Identity::Password(UserAndPassword {
-                    user: IdOrName::Name("admin".to_string()),
-                    password: "devstacker".to_string(),
-                    domain: Some(IdOrName::from_name("Default")),
-                }),
-                scope: None,
-            },
-        };
-        test::compare(PASSWORD_NAME_UNSCOPED, value);
+    const APPLICATION_CREDENTIAL_ID: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "application_credential"
+            ],
+            "application_credential": {
+                "id": "423f19a4ac1e4f48bbb4180756e6eb6c",
+                "secret": "rEaqvJka48mpv"
+            }
+        }
     }
+}"#;
 
-    #[test]
-    fn test_password_id_scoped_with_id() {
+    const APPLICATION_CREDENTIAL_NAME_USER_NAME: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "application_credential"
+            ],
+            "application_credential": {
+                "name": "monitoring",
+                "secret": "rEaqvJka48mpv",
+                "user": {
+                    "name": "demo",
+                    "domain": {
+                        "name": "Default"
+                    }
+                }
+            }
+        }
+    }
+}"#;
+
+    const APPLICATION_CREDENTIAL_NAME_USER_ID: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "application_credential"
+            ],
+            "application_credential": {
+                "name": "monitoring",
+                "secret": "rEaqvJka48mpv",
+                "user": {
+                    "id": "ee4dfb6e5540447cb3741905149d9b6e"
+                }
+            }
+        }
+    }
+}"#;
+
+    const TOTP_ID: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "totp"
+            ],
+            "totp": {
+                "user": {
+                    "id": "ee4dfb6e5540447cb3741905149d9b6e",
+                    "passcode": "123456"
+                }
+            }
+        }
+    }
+}"#;
+
+    const PASSWORD_AND_TOTP_SCOPED: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "password",
+                "totp"
+            ],
+            "password": {
+                "user": {
+                    "name": "admin",
+                    "domain": {
+                        "name": "Default"
+                    },
+                    "password": "devstacker"
+                }
+            },
+            "totp": {
+                "user": {
+                    "name": "admin",
+                    "domain": {
+                        "name": "Default"
+                    },
+                    "passcode": "123456"
+                }
+            }
+        },
+        "scope": {
+            "project": {
+                "name": "admin",
+                "domain": {
+                    "name": "Default"
+                }
+            }
+        }
+    }
+}"#;
+
+    const RECEIPT: &str = r#"
+{
+    "receipt": {
+        "methods": [
+            "password"
+        ],
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin",
+            "password_expires_at": "2016-11-06T15:32:17.000000"
+        },
+        "expires_at": "2015-11-09T01:42:57.527363Z",
+        "issued_at": "2015-11-09T00:42:57.527404Z"
+    },
+    "required_auth_methods": [
+        ["password", "totp"],
+        ["password", "application_credential", "totp"]
+    ]
+}"#;
+
+    const TOKEN_PROJECT_SCOPED: &str = r#"
+{
+    "token": {
+        "methods": [
+            "password"
+        ],
+        "roles": [
+            {
+                "id": "51cc68287d524c759f47c811e6463340",
+                "name": "admin"
+            },
+            {
+                "id": "9fe2ff9ee4384b1894a90878d3e92bab",
+                "name": "member"
+            }
+        ],
+        "expires_at": "2015-11-07T02:58:43.578887Z",
+        "project": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "a6944d763bf64ee6a275f1263fae0352",
+            "name": "admin"
+        },
+        "is_domain": false,
+        "catalog": [
+            {
+                "endpoints": [
+                    {
+                        "region_id": "RegionOne",
+                        "url": "http://example.com/identity",
+                        "region": "RegionOne",
+                        "interface": "public",
+                        "id": "068d1b359ee84b438266cb736d81de97"
+                    },
+                    {
+                        "region_id": "RegionOne",
+                        "url": "http://example.com/identity",
+                        "region": "RegionOne",
+                        "interface": "admin",
+                        "id": "8bfc846841ab441ca38471be6d164ced"
+                    }
+                ],
+                "type": "identity",
+                "id": "050726f278654128aba89757ae25910c",
+                "name": "keystone"
+            },
+            {
+                "endpoints": [
+                    {
+                        "region_id": "RegionOne",
+                        "url": "http://example.com/compute/v2.1",
+                        "region": "RegionOne",
+                        "interface": "public",
+                        "id": "2dc6b3e9b98446ec86ef45c1acf1a4d1"
+                    }
+                ],
+                "type": "compute",
+                "id": "1ddf0c9d8d8d4b06a2d9d6b3d1e0d6b2",
+                "name": "nova"
+            }
+        ],
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin",
+            "password_expires_at": "2016-11-06T15:32:17.000000"
+        },
+        "audit_ids": [
+            "3T2dc1CGQxyJsHdDu1xkcw"
+        ],
+        "issued_at": "2015-11-07T01:58:43.578929Z"
+    }
+}"#;
+
+    const TOKEN_DOMAIN_SCOPED: &str = r#"
+{
+    "token": {
+        "methods": [
+            "token",
+            "password"
+        ],
+        "roles": [
+            {
+                "id": "51cc68287d524c759f47c811e6463340",
+                "name": "admin"
+            }
+        ],
+        "expires_at": "2015-11-07T02:58:43.578887Z",
+        "domain": {
+            "id": "default",
+            "name": "Default"
+        },
+        "catalog": [],
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin",
+            "password_expires_at": null
+        },
+        "audit_ids": [
+            "VcxU2JYqT8OzfUVvrjEITQ",
+            "qNUTIJntTzO1-XUk5STybw"
+        ],
+        "issued_at": "2015-11-07T01:58:43.578929Z"
+    }
+}"#;
+
+    const TOKEN_SYSTEM_SCOPED: &str = r#"
+{
+    "token": {
+        "methods": [
+            "password"
+        ],
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin",
+            "password_expires_at": null
+        },
+        "audit_ids": [
+            "Xpa6Uyn-T9S6mTREudUH3w"
+        ],
+        "expires_at": "2018-04-06T00:50:48.000000Z",
+        "issued_at": "2018-04-05T23:50:48.000000Z",
+        "system": {
+            "all": true
+        },
+        "roles": [
+            {
+                "id": "2b7c1f8f21b8406da76b6f86cc8e0c8d",
+                "name": "admin"
+            }
+        ],
+        "catalog": []
+    }
+}"#;
+
+    const TOKEN_UNSCOPED: &str = r#"
+{
+    "token": {
+        "methods": [
+            "password"
+        ],
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin",
+            "password_expires_at": "2016-11-06T15:32:17.000000"
+        },
+        "audit_ids": [
+            "lC2Wj1jbQe-dLjLyOx4qPQ"
+        ],
+        "expires_at": "2015-11-05T22:00:11.000000Z",
+        "issued_at": "2015-11-05T21:00:33.819948Z"
+    }
+}"#;
+
+    const TOKEN_APPLICATION_CREDENTIAL: &str = r#"
+{
+    "token": {
+        "methods": [
+            "application_credential"
+        ],
+        "roles": [
+            {
+                "id": "9fe2ff9ee4384b1894a90878d3e92bab",
+                "name": "member"
+            }
+        ],
+        "expires_at": "2018-02-21T22:02:49.000000Z",
+        "issued_at": "2018-02-21T21:02:49.000000Z",
+        "project": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "231c62fb0fbd485b995e8b060c3f0d98",
+            "name": "demo"
+        },
+        "is_domain": false,
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "fd786d56402c4d1691372e7dee0d00b5",
+            "name": "demo",
+            "password_expires_at": null
+        },
+        "audit_ids": [
+            "9JsolhssRzKfyrsz6o_VSQ"
+        ],
+        "application_credential": {
+            "id": "9a4e1a3d9d4f4ab4b1c5ffc5a4ad9d8d",
+            "name": "monitoring",
+            "restricted": true,
+            "access_rules": [
+                {
+                    "id": "07d719df00f349ef8de77d542edf010c",
+                    "service": "compute",
+                    "method": "GET",
+                    "path": "/v2.1/servers"
+                }
+            ]
+        },
+        "catalog": []
+    }
+}"#;
+
+    const TOKEN_TRUST_SCOPED: &str = r#"
+{
+    "token": {
+        "methods": [
+            "password"
+        ],
+        "roles": [
+            {
+                "id": "9fe2ff9ee4384b1894a90878d3e92bab",
+                "name": "member"
+            }
+        ],
+        "expires_at": "2018-02-21T22:02:49.000000Z",
+        "issued_at": "2018-02-21T21:02:49.000000Z",
+        "project": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "231c62fb0fbd485b995e8b060c3f0d98",
+            "name": "demo"
+        },
+        "is_domain": false,
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "3ec3164f750146be97f21559ee4d9c51",
+            "name": "heat",
+            "password_expires_at": null
+        },
+        "audit_ids": [
+            "wUUAcMmSQ2mmBDlzjN9A8Q"
+        ],
+        "OS-TRUST:trust": {
+            "id": "fe0aef",
+            "impersonation": false,
+            "trustee_user": {
+                "id": "3ec3164f750146be97f21559ee4d9c51"
+            },
+            "trustor_user": {
+                "id": "fd786d56402c4d1691372e7dee0d00b5"
+            }
+        },
+        "catalog": []
+    }
+}"#;
+
+    const TOKEN_TRUST_SCOPE: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "token"
+            ],
+            "token": {
+                "id": "abcdef"
+            }
+        },
+        "scope": {
+            "OS-TRUST:trust": {
+                "id": "fe0aef"
+            }
+        }
+    }
+}"#;
+
+    const MAPPED: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "mapped"
+            ],
+            "mapped": {
+                "identity_provider": "myidp",
+                "protocol": "saml2"
+            }
+        }
+    }
+}"#;
+
+    const OPENID_SCOPED: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "openid"
+            ],
+            "openid": {
+                "identity_provider": "keycloak",
+                "protocol": "openid"
+            }
+        },
+        "scope": {
+            "project": {
+                "id": "231c62fb0fbd485b995e8b060c3f0d98"
+            }
+        }
+    }
+}"#;
+
+    const TOKEN_SERVICE_PROVIDER_SCOPE: &str = r#"
+{
+    "auth": {
+        "identity": {
+            "methods": [
+                "token"
+            ],
+            "token": {
+                "id": "abcdef"
+            }
+        },
+        "scope": {
+            "service_provider": {
+                "id": "remote-cloud"
+            }
+        }
+    }
+}"#;
+
+    const TOKEN_FEDERATED: &str = r#"
+{
+    "token": {
+        "methods": [
+            "mapped"
+        ],
+        "user": {
+            "domain": {
+                "id": "Federated",
+                "name": "Federated"
+            },
+            "id": "8f3b0c4a9e5d4b7c9d0e1f2a3b4c5d6e",
+            "name": "alice",
+            "OS-FEDERATION": {
+                "identity_provider": {
+                    "id": "myidp"
+                },
+                "protocol": {
+                    "id": "saml2"
+                },
+                "groups": [
+                    {
+                        "id": "f3b54e8d2c1a4b0e9d7c6a5b4e3d2c1b"
+                    }
+                ]
+            }
+        },
+        "audit_ids": [
+            "lC2Wj1jbQe-dLjLyOx4qPQ"
+        ],
+        "expires_at": "2015-11-05T22:00:11.000000Z",
+        "issued_at": "2015-11-05T21:00:33.819948Z"
+    }
+}"#;
+
+    #[test]
+    fn test_password_name_unscoped() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Password(UserAndPassword {
+                    user: IdOrName::Name("admin".to_string()),
+                    password: "devstacker".to_string(),
+                    domain: Some(IdOrName::from_name("Default")),
+                }),
+                scope: None,
+            },
+        };
+        test::compare(PASSWORD_NAME_UNSCOPED, value);
+    }
+
+    #[test]
+    fn test_password_id_scoped_with_id() {
         let value = AuthRoot {
             auth: Auth {
                 identity: Identity::Password(UserAndPassword {
@@ -320,4 +1583,522 @@ mod test {
         };
         test::compare(TOKEN_SCOPED_WITH_NAME, value);
     }
+
+    #[test]
+    fn test_application_credential_id() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::ApplicationCredential(ApplicationCredential::from_id(
+                    "423f19a4ac1e4f48bbb4180756e6eb6c",
+                    "rEaqvJka48mpv",
+                )),
+                scope: None,
+            },
+        };
+        test::compare(APPLICATION_CREDENTIAL_ID, value);
+    }
+
+    #[test]
+    fn test_application_credential_name_user_name() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::ApplicationCredential(ApplicationCredential {
+                    application_credential: IdOrName::from_name("monitoring"),
+                    secret: "rEaqvJka48mpv".to_string(),
+                    user: Some(User {
+                        user: IdOrName::from_name("demo"),
+                        domain: Some(IdOrName::from_name("Default")),
+                    }),
+                }),
+                scope: None,
+            },
+        };
+        test::compare(APPLICATION_CREDENTIAL_NAME_USER_NAME, value);
+    }
+
+    #[test]
+    fn test_application_credential_name_user_id() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::ApplicationCredential(ApplicationCredential::from_name(
+                    "monitoring",
+                    User {
+                        user: IdOrName::from_id("ee4dfb6e5540447cb3741905149d9b6e"),
+                        domain: None,
+                    },
+                    "rEaqvJka48mpv",
+                )),
+                scope: None,
+            },
+        };
+        test::compare(APPLICATION_CREDENTIAL_NAME_USER_ID, value);
+    }
+
+    #[test]
+    fn test_totp_id() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Totp(UserAndPasscode {
+                    user: IdOrName::from_id("ee4dfb6e5540447cb3741905149d9b6e"),
+                    passcode: "123456".to_string(),
+                    domain: None,
+                }),
+                scope: None,
+            },
+        };
+        test::compare(TOTP_ID, value);
+    }
+
+    #[test]
+    fn test_password_and_totp_scoped() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Multiple(vec![
+                    Identity::Password(UserAndPassword {
+                        user: IdOrName::from_name("admin"),
+                        password: "devstacker".to_string(),
+                        domain: Some(IdOrName::from_name("Default")),
+                    }),
+                    Identity::Totp(UserAndPasscode {
+                        user: IdOrName::from_name("admin"),
+                        passcode: "123456".to_string(),
+                        domain: Some(IdOrName::from_name("Default")),
+                    }),
+                ]),
+                scope: Some(Scope::Project(Project {
+                    project: IdOrName::from_name("admin"),
+                    domain: Some(IdOrName::from_name("Default")),
+                })),
+            },
+        };
+        test::compare(PASSWORD_AND_TOTP_SCOPED, value);
+    }
+
+    #[test]
+    fn test_multiple_nested_methods() {
+        let identity = Identity::Multiple(vec![
+            Identity::Token("abcdef".to_string()),
+            Identity::Multiple(vec![Identity::Totp(UserAndPasscode {
+                user: IdOrName::from_id("ee4dfb6e5540447cb3741905149d9b6e"),
+                passcode: "123456".to_string(),
+                domain: None,
+            })]),
+        ]);
+        assert_eq!(identity.methods(), vec!["token", "totp"]);
+        let value = serde_json::to_value(identity).unwrap();
+        assert_eq!(value["methods"], serde_json::json!(["token", "totp"]));
+        assert_eq!(value["token"]["id"], "abcdef");
+        assert_eq!(value["totp"]["user"]["passcode"], "123456");
+    }
+
+    #[test]
+    fn test_multiple_duplicate_method() {
+        let identity = Identity::Multiple(vec![
+            Identity::Token("abcdef".to_string()),
+            Identity::Token("123456".to_string()),
+        ]);
+        assert!(serde_json::to_value(identity).is_err());
+    }
+
+    #[test]
+    fn test_multiple_empty() {
+        assert!(serde_json::to_value(Identity::Multiple(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_receipt() {
+        let root: ReceiptRoot = serde_json::from_str(RECEIPT).unwrap();
+        assert_eq!(root.receipt.methods, vec!["password".to_string()]);
+        assert_eq!(root.receipt.user.id, "ee4dfb6e5540447cb3741905149d9b6e");
+        assert_eq!(root.receipt.user.domain.name, "Default");
+        assert!(root.receipt.user.password_expires_at.is_some());
+        assert_eq!(
+            root.missing_methods(),
+            vec![vec!["totp"], vec!["application_credential", "totp"]]
+        );
+    }
+
+    #[test]
+    fn test_token_project_scoped() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_PROJECT_SCOPED).unwrap();
+        let token = root.token;
+        assert_eq!(token.methods, vec!["password".to_string()]);
+        assert_eq!(token.roles.len(), 2);
+        assert_eq!(token.user.name, "admin");
+        assert_eq!(token.user.domain.id, "default");
+        assert!(token.user.password_expires_at.is_some());
+        assert_eq!(token.audit_ids, vec!["3T2dc1CGQxyJsHdDu1xkcw".to_string()]);
+        assert!(!token.is_domain);
+        assert_eq!(token.catalog.len(), 2);
+        let identity = &token.catalog[0];
+        assert_eq!(
+            identity.id.as_ref().unwrap(),
+            "050726f278654128aba89757ae25910c"
+        );
+        assert_eq!(identity.name.as_ref().unwrap(), "keystone");
+        assert_eq!(identity.endpoints[0].interface, Interface::Public);
+        assert_eq!(identity.endpoints[1].interface, Interface::Admin);
+        assert_eq!(
+            identity.endpoints[1].id.as_ref().unwrap(),
+            "8bfc846841ab441ca38471be6d164ced"
+        );
+        assert_eq!(
+            identity.endpoints[1].region_id.as_ref().unwrap(),
+            "RegionOne"
+        );
+        assert!(identity.endpoints[1].enabled);
+        assert!(token.issued_at < token.expires_at);
+        assert!(token.trust.is_none());
+        assert!(token.application_credential.is_none());
+        match token.scope() {
+            TokenScope::Project(project) => {
+                assert_eq!(project.id, "a6944d763bf64ee6a275f1263fae0352");
+                assert_eq!(project.name, "admin");
+                assert_eq!(project.domain.name, "Default");
+            }
+            other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_domain_scoped() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_DOMAIN_SCOPED).unwrap();
+        let token = root.token;
+        assert_eq!(token.methods.len(), 2);
+        assert_eq!(token.audit_ids.len(), 2);
+        assert!(token.user.password_expires_at.is_none());
+        match token.scope() {
+            TokenScope::Domain(domain) => assert_eq!(domain.id, "default"),
+            other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_system_scoped() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_SYSTEM_SCOPED).unwrap();
+        match root.token.scope() {
+            TokenScope::System(system) => assert!(system.all),
+            other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_unscoped() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_UNSCOPED).unwrap();
+        let token = root.token;
+        assert!(token.roles.is_empty());
+        assert!(token.catalog.is_empty());
+        match token.scope() {
+            TokenScope::Unscoped => (),
+            other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_application_credential() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_APPLICATION_CREDENTIAL).unwrap();
+        let cred = root.token.application_credential.unwrap();
+        assert_eq!(cred.id, "9a4e1a3d9d4f4ab4b1c5ffc5a4ad9d8d");
+        assert_eq!(cred.name, "monitoring");
+        assert!(cred.restricted);
+        let rules = cred.access_rules.unwrap();
+        assert!(rules[0].matches("compute", "GET", "/v2.1/servers"));
+    }
+
+    #[test]
+    fn test_token_trust_scoped() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_TRUST_SCOPED).unwrap();
+        let token = root.token;
+        let trust = token.trust.as_ref().unwrap();
+        assert_eq!(trust.id, "fe0aef");
+        assert!(!trust.impersonation);
+        assert_eq!(trust.trustee_user.id, token.user.id);
+        assert_eq!(trust.trustor_user.id, "fd786d56402c4d1691372e7dee0d00b5");
+        match token.scope() {
+            TokenScope::Project(project) => assert_eq!(project.name, "demo"),
+            other => panic!("Unexpected scope {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_interface() {
+        for (s, iface) in &[
+            ("public", Interface::Public),
+            ("internal", Interface::Internal),
+            ("admin", Interface::Admin),
+            ("private", Interface::Other("private".to_string())),
+        ] {
+            let parsed: Interface = serde_json::from_value(serde_json::json!(s)).unwrap();
+            assert_eq!(&parsed, iface);
+            assert_eq!(Interface::from(*s), *iface);
+            assert_eq!(iface.to_string(), *s);
+            assert_eq!(serde_json::to_value(iface).unwrap(), serde_json::json!(s));
+        }
+    }
+
+    #[test]
+    fn test_endpoint_minimal() {
+        let endpoint: Endpoint = serde_json::from_str(
+            r#"{"interface": "internal", "region": "RegionOne", "url": "http://10.0.0.1"}"#,
+        )
+        .unwrap();
+        assert!(endpoint.id.is_none());
+        assert!(endpoint.region_id.is_none());
+        assert_eq!(endpoint.interface, Interface::Internal);
+        assert!(endpoint.enabled);
+    }
+
+    #[test]
+    fn test_token_trust_scope() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Token("abcdef".to_string()),
+                scope: Some(Scope::Trust(IdRef {
+                    id: "fe0aef".to_string(),
+                })),
+            },
+        };
+        test::compare(TOKEN_TRUST_SCOPE, value);
+    }
+
+    #[test]
+    fn test_mapped() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Mapped(Federated::new("myidp", "saml2")),
+                scope: None,
+            },
+        };
+        test::compare(MAPPED, value);
+    }
+
+    #[test]
+    fn test_openid_scoped() {
+        let root: AuthRoot = serde_json::from_str(OPENID_SCOPED).unwrap();
+        assert_eq!(root.auth.identity.methods(), vec!["openid"]);
+        match root.auth.identity {
+            Identity::OpenId(ref federated) => {
+                assert_eq!(federated.identity_provider, "keycloak");
+                assert_eq!(
+                    federated.auth_path(),
+                    "OS-FEDERATION/identity_providers/keycloak/protocols/openid/auth"
+                );
+            }
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_token_service_provider_scope() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Token("abcdef".to_string()),
+                scope: Some(Scope::ServiceProvider(IdRef {
+                    id: "remote-cloud".to_string(),
+                })),
+            },
+        };
+        test::compare(TOKEN_SERVICE_PROVIDER_SCOPE, value);
+    }
+
+    #[test]
+    fn test_token_federated() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_FEDERATED).unwrap();
+        let federation = root.token.user.federation.unwrap();
+        assert_eq!(federation.identity_provider.id, "myidp");
+        assert_eq!(federation.protocol.id, "saml2");
+        assert_eq!(federation.groups.len(), 1);
+        let root: TokenRoot = serde_json::from_str(TOKEN_UNSCOPED).unwrap();
+        assert!(root.token.user.federation.is_none());
+    }
+
+    #[test]
+    fn test_rescope() {
+        let value = AuthRoot::rescope(
+            "abcdef",
+            Scope::Trust(IdRef {
+                id: "fe0aef".to_string(),
+            }),
+        );
+        test::compare(TOKEN_TRUST_SCOPE, value);
+    }
+
+    fn clock(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_token_lifetime() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_UNSCOPED).unwrap();
+        let token = &root.token;
+        assert_eq!(
+            token.lifetime(),
+            Duration::seconds(3577) + Duration::microseconds(180052)
+        );
+        let now = clock("2015-11-05T21:30:11Z");
+        assert_eq!(token.remaining_lifetime(&now), Duration::minutes(30));
+        assert!(!token.is_expired(&now));
+        assert!(!token.is_expired(&clock("2015-11-05T22:00:10Z")));
+        assert!(token.is_expired(&clock("2015-11-05T22:00:11Z")));
+        assert!(token.is_expired(&clock("2015-11-06T00:00:00Z")));
+        assert!(token.remaining_lifetime(&clock("2015-11-05T22:00:12Z")) < Duration::zero());
+        assert!(token.is_expired(&SystemClock));
+    }
+
+    #[test]
+    fn test_token_renewal() {
+        let root: TokenRoot = serde_json::from_str(TOKEN_UNSCOPED).unwrap();
+        let token = &root.token;
+        let margin = Duration::seconds(DEFAULT_RENEWAL_MARGIN);
+        assert_eq!(
+            token.renew_at(margin),
+            DateTime::parse_from_rfc3339("2015-11-05T21:59:41Z").unwrap()
+        );
+        assert!(!token.needs_renewal(&clock("2015-11-05T21:59:40Z"), margin));
+        assert!(token.needs_renewal(&clock("2015-11-05T21:59:41Z"), margin));
+        assert!(!root.needs_renewal(&clock("2015-11-05T21:59:40Z")));
+        assert!(root.needs_renewal(&clock("2015-11-05T21:59:41Z")));
+
+        // The margin is capped at half of the lifetime.
+        let margin = Duration::hours(2);
+        assert_eq!(
+            token.renew_at(margin),
+            token.expires_at - token.lifetime() / 2
+        );
+        assert!(!token.needs_renewal(&clock("2015-11-05T21:00:34Z"), margin));
+        assert!(token.needs_renewal(&clock("2015-11-05T21:31:00Z"), margin));
+    }
+
+    #[test]
+    fn test_auth_round_trip() {
+        for sample in &[
+            PASSWORD_NAME_UNSCOPED,
+            PASSWORD_ID_SCOPED_WITH_ID,
+            PASSWORD_ID_SYSTEM_SCOPE,
+            TOKEN_SCOPED_WITH_NAME,
+            APPLICATION_CREDENTIAL_ID,
+            APPLICATION_CREDENTIAL_NAME_USER_NAME,
+            APPLICATION_CREDENTIAL_NAME_USER_ID,
+            TOTP_ID,
+            PASSWORD_AND_TOTP_SCOPED,
+            TOKEN_TRUST_SCOPE,
+            MAPPED,
+            OPENID_SCOPED,
+            TOKEN_SERVICE_PROVIDER_SCOPE,
+        ] {
+            test::round_trip::<AuthRoot>(sample);
+        }
+    }
+
+    #[test]
+    fn test_auth_deserialize() {
+        let root: AuthRoot = serde_json::from_str(PASSWORD_AND_TOTP_SCOPED).unwrap();
+        assert_eq!(root.auth.identity.methods(), vec!["password", "totp"]);
+        match root.auth.identity {
+            Identity::Multiple(ref items) => match items[0] {
+                Identity::Password(ref user) => {
+                    assert_eq!(user.user, IdOrName::from_name("admin"));
+                    assert_eq!(user.password, "devstacker");
+                    assert_eq!(user.domain, Some(IdOrName::from_name("Default")));
+                }
+                ref other => panic!("Unexpected identity {:?}", other),
+            },
+            ref other => panic!("Unexpected identity {:?}", other),
+        }
+        match root.auth.scope {
+            Some(Scope::Project(ref project)) => {
+                assert_eq!(project.project, IdOrName::from_name("admin"));
+            }
+            ref other => panic!("Unexpected scope {:?}", other),
+        }
+
+        let root: AuthRoot = serde_json::from_str(PASSWORD_ID_SYSTEM_SCOPE).unwrap();
+        assert!(matches!(root.auth.scope, Some(Scope::System)));
+    }
+
+    #[test]
+    fn test_id_or_name_deserialize() {
+        let value: IdOrName = serde_json::from_str(r#"{"id": "abcdef"}"#).unwrap();
+        assert_eq!(value, IdOrName::from_id("abcdef"));
+        let value: IdOrName = serde_json::from_str(r#"{"name": "Default"}"#).unwrap();
+        assert_eq!(value, IdOrName::from_name("Default"));
+        let value: IdOrName =
+            serde_json::from_str(r#"{"name": "Default", "id": "default"}"#).unwrap();
+        assert_eq!(value, IdOrName::from_id("default"));
+        assert!(serde_json::from_str::<IdOrName>("{}").is_err());
+    }
+
+    #[test]
+    fn test_identity_deserialize_errors() {
+        for sample in &[
+            r#"{"methods": []}"#,
+            r#"{"methods": ["kerberos"]}"#,
+            r#"{"methods": ["token"]}"#,
+            r#"{"methods": ["token", "token"], "token": {"id": "abcdef"}}"#,
+        ] {
+            assert!(serde_json::from_str::<Identity>(sample).is_err());
+        }
+    }
+
+    #[test]
+    fn test_system_scope_not_all() {
+        let res = serde_json::from_str::<Scope>(r#"{"system": {"all": false}}"#);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let value = AuthRoot {
+            auth: Auth {
+                identity: Identity::Multiple(vec![
+                    Identity::Password(UserAndPassword {
+                        user: IdOrName::from_name("admin"),
+                        password: "devstacker".to_string(),
+                        domain: Some(IdOrName::from_name("Default")),
+                    }),
+                    Identity::Token("abcdef".to_string()),
+                    Identity::ApplicationCredential(ApplicationCredential {
+                        application_credential: IdOrName::from_id("423f19a4ac1e4f48"),
+                        secret: "rEaqvJka48mpv".to_string(),
+                        user: None,
+                    }),
+                    Identity::Totp(UserAndPasscode {
+                        user: IdOrName::from_name("admin"),
+                        passcode: "123456".to_string(),
+                        domain: Some(IdOrName::from_name("Default")),
+                    }),
+                ]),
+                scope: None,
+            },
+        };
+        let s = format!("{:?}", value);
+        for secret in &["devstacker", "abcdef", "rEaqvJka48mpv", "123456"] {
+            assert!(!s.contains(secret), "{} found in {}", secret, s);
+        }
+        assert!(s.contains("admin"));
+        assert!(s.contains("423f19a4ac1e4f48"));
+    }
+
+    #[test]
+    fn test_serialize_redacted() {
+        let root: AuthRoot = serde_json::from_str(PASSWORD_AND_TOTP_SCOPED).unwrap();
+        let value = serde_json::to_value(root.redacted()).unwrap();
+        let identity = &value["auth"]["identity"];
+        assert_eq!(identity["password"]["user"]["password"], REDACTED);
+        assert_eq!(identity["password"]["user"]["name"], "admin");
+        assert_eq!(identity["totp"]["user"]["passcode"], REDACTED);
+        assert_eq!(value["auth"]["scope"]["project"]["name"], "admin");
+
+        let root: AuthRoot = serde_json::from_str(TOKEN_SCOPED_WITH_NAME).unwrap();
+        let value = serde_json::to_value(root.redacted()).unwrap();
+        assert_eq!(value["auth"]["identity"]["token"]["id"], REDACTED);
+
+        let root: AuthRoot = serde_json::from_str(APPLICATION_CREDENTIAL_ID).unwrap();
+        let value = serde_json::to_value(root.redacted()).unwrap();
+        let cred = &value["auth"]["identity"]["application_credential"];
+        assert_eq!(cred["secret"], REDACTED);
+        assert_eq!(cred["id"], "423f19a4ac1e4f48bbb4180756e6eb6c");
+    }
 }
@@ -0,0 +1,489 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Application credentials and access rules JSON structures.
+
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::super::common::{self, ResourceLinks};
+use super::roles::Role;
+use super::{IdOrName, REDACTED};
+
+/// An access rule in a creation request.
+///
+/// Either references an existing rule by its ID or defines a new one.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AccessRuleCreate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+    /// Service type, e.g. `compute`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub service: Option<String>,
+    /// HTTP method in upper case.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub method: Option<String>,
+    /// Path template, may contain `*`, `**` and `{placeholder}`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+}
+
+/// An access rule.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessRule {
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Service type, e.g. `compute`.
+    pub service: String,
+    /// HTTP method in upper case.
+    pub method: String,
+    /// Path template, may contain `*`, `**` and `{placeholder}`.
+    pub path: String,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// An access rule response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessRuleRoot {
+    pub access_rule: AccessRule,
+}
+
+/// A list of access rules.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessRulesRoot {
+    pub access_rules: Vec<AccessRule>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A request to create an application credential.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ApplicationCredentialCreate {
+    pub name: String,
+    /// Secret to use, generated by the server if missing.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
+    /// Roles to delegate, all roles of the current token if missing.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub roles: Option<Vec<IdOrName>>,
+    /// Whether the credential can create other application credentials and trusts.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub unrestricted: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub access_rules: Option<Vec<AccessRuleCreate>>,
+}
+
+/// An application credential creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApplicationCredentialCreateRoot {
+    pub application_credential: ApplicationCredentialCreate,
+}
+
+/// An application credential.
+#[derive(Clone, Deserialize)]
+pub struct ApplicationCredential {
+    pub id: String,
+    pub name: String,
+    /// Secret, only returned once on creation.
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(deserialize_with = "common::opt_datetime_as_utc", default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    #[serde(default)]
+    pub unrestricted: bool,
+    /// Access rules, `None` or an empty list means no restrictions.
+    #[serde(default)]
+    pub access_rules: Option<Vec<AccessRule>>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// An application credential response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApplicationCredentialRoot {
+    pub application_credential: ApplicationCredential,
+}
+
+/// A list of application credentials.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApplicationCredentialsRoot {
+    pub application_credentials: Vec<ApplicationCredential>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing application credentials.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ApplicationCredentialFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl AccessRuleCreate {
+    /// A new access rule.
+    pub fn new<S1, S2, S3>(service: S1, method: S2, path: S3) -> AccessRuleCreate
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+        S3: Into<String>,
+    {
+        AccessRuleCreate {
+            id: None,
+            service: Some(service.into()),
+            method: Some(method.into()),
+            path: Some(path.into()),
+        }
+    }
+
+    /// A reference to an existing access rule.
+    pub fn from_id<S: Into<String>>(id: S) -> AccessRuleCreate {
+        AccessRuleCreate {
+            id: Some(id.into()),
+            ..AccessRuleCreate::default()
+        }
+    }
+}
+
+impl AccessRule {
+    /// Whether the rule allows the request.
+    ///
+    /// In the path template `*` and `{placeholder}` match exactly one path segment, `**`
+    /// matches any number of characters including `/`.
+    pub fn matches(&self, service: &str, method: &str, path: &str) -> bool {
+        self.service == service && self.method == method && path_matches(&self.path, path)
+    }
+}
+
+impl ApplicationCredentialCreate {
+    /// A copy with the secret replaced by a placeholder.
+    pub fn redacted(&self) -> ApplicationCredentialCreate {
+        ApplicationCredentialCreate {
+            secret: self.secret.as_ref().map(|_| REDACTED.to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for ApplicationCredentialCreate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApplicationCredentialCreate")
+            .field("name", &self.name)
+            .field("secret", &self.secret.as_ref().map(|_| REDACTED))
+            .field("description", &self.description)
+            .field("expires_at", &self.expires_at)
+            .field("roles", &self.roles)
+            .field("unrestricted", &self.unrestricted)
+            .field("access_rules", &self.access_rules)
+            .finish()
+    }
+}
+
+impl ApplicationCredential {
+    /// Whether the access rules of the credential allow the request.
+    pub fn allows(&self, service: &str, method: &str, path: &str) -> bool {
+        match self.access_rules {
+            Some(ref rules) if !rules.is_empty() => {
+                find_access_rule(rules, service, method, path).is_some()
+            }
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Debug for ApplicationCredential {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApplicationCredential")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("secret", &self.secret.as_ref().map(|_| REDACTED))
+            .field("description", &self.description)
+            .field("expires_at", &self.expires_at)
+            .field("project_id", &self.project_id)
+            .field("roles", &self.roles)
+            .field("unrestricted", &self.unrestricted)
+            .field("access_rules", &self.access_rules)
+            .field("links", &self.links)
+            .finish()
+    }
+}
+
+impl ApplicationCredentialsRoot {
+    /// Find an application credential by its ID or name.
+    pub fn find(&self, value: &IdOrName) -> Option<&ApplicationCredential> {
+        self.application_credentials
+            .iter()
+            .find(|cred| match value {
+                IdOrName::Id(id) => &cred.id == id,
+                IdOrName::Name(name) => &cred.name == name,
+            })
+    }
+}
+
+/// Find the first access rule that allows the request.
+pub fn find_access_rule<'r, I>(
+    rules: I,
+    service: &str,
+    method: &str,
+    path: &str,
+) -> Option<&'r AccessRule>
+where
+    I: IntoIterator<Item = &'r AccessRule>,
+{
+    rules
+        .into_iter()
+        .find(|rule| rule.matches(service, method, path))
+}
+
+/// Match a path against a template the way `keystonemiddleware` does.
+fn path_matches(template: &str, path: &str) -> bool {
+    let mut pattern = String::from("^");
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("**") {
+            pattern.push_str(".*");
+            rest = &rest[2..];
+        } else if c == '*' {
+            pattern.push_str("[^/]+");
+            rest = &rest[1..];
+        } else if let (true, Some(end)) = (c == '{', rest.find('}')) {
+            pattern.push_str("[^/]+");
+            rest = &rest[end + 1..];
+        } else {
+            pattern.push_str(&regex::escape(&rest[..c.len_utf8()]));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern)
+        .map(|re| re.is_match(path))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::*;
+
+    const APPLICATION_CREDENTIAL_CREATE: &str = r#"
+{
+    "application_credential": {
+        "name": "monitoring",
+        "secret": "rEaqvJka48mpv",
+        "description": "Application credential for monitoring.",
+        "expires_at": "2018-02-27T18:30:59Z",
+        "roles": [
+            {"name": "Reader"}
+        ],
+        "access_rules": [
+            {
+                "path": "/v2.1/servers",
+                "method": "GET",
+                "service": "compute"
+            },
+            {
+                "id": "07d719df00f349ef8de77d542edf010c"
+            }
+        ],
+        "unrestricted": false
+    }
+}"#;
+
+    const APPLICATION_CREDENTIAL: &str = r#"
+{
+    "application_credential": {
+        "description": "Application credential for monitoring.",
+        "roles": [
+            {
+                "id": "6aff702516544aeca22817fd3bc39683",
+                "domain_id": null,
+                "name": "Reader"
+            }
+        ],
+        "access_rules": [
+            {
+                "path": "/v2.1/servers",
+                "id": "07d719df00f349ef8de77d542edf010c",
+                "service": "compute",
+                "method": "GET"
+            }
+        ],
+        "links": {
+            "self": "http://example.com/identity/v3/users/fd786d56402c4d1691372e7dee0d00b5/application_credentials/58d61ff8e6e34accb35874016d1dba8b"
+        },
+        "expires_at": "2018-02-27T18:30:59.000000",
+        "unrestricted": false,
+        "secret": "rEaqvJka48mpv",
+        "project_id": "231c62fb0fbd485b995e8b060c3f0d98",
+        "id": "58d61ff8e6e34accb35874016d1dba8b",
+        "name": "monitoring"
+    }
+}"#;
+
+    const ACCESS_RULES: &str = r#"
+{
+    "links": {
+        "self": "http://example.com/identity/v3/users/fd786d56402c4d1691372e7dee0d00b5/access_rules",
+        "previous": null,
+        "next": null
+    },
+    "access_rules": [
+        {
+            "path": "/v2.1/servers/{server_id}/ips",
+            "links": {
+                "self": "http://example.com/identity/v3/access_rules/07d719df00f349ef8de77d542edf010c"
+            },
+            "id": "07d719df00f349ef8de77d542edf010c",
+            "service": "compute",
+            "method": "GET"
+        },
+        {
+            "path": "/v2/images/**",
+            "id": "4dd1cfe7e5ce4a53ba5a37a70ae0e6c1",
+            "service": "image",
+            "method": "GET"
+        }
+    ]
+}"#;
+
+    #[test]
+    fn test_application_credential_create() {
+        let value = ApplicationCredentialCreateRoot {
+            application_credential: ApplicationCredentialCreate {
+                name: "monitoring".to_string(),
+                secret: Some("rEaqvJka48mpv".to_string()),
+                description: Some("Application credential for monitoring.".to_string()),
+                expires_at: Some(DateTime::parse_from_rfc3339("2018-02-27T18:30:59Z").unwrap()),
+                roles: Some(vec![IdOrName::from_name("Reader")]),
+                unrestricted: Some(false),
+                access_rules: Some(vec![
+                    AccessRuleCreate::new("compute", "GET", "/v2.1/servers"),
+                    AccessRuleCreate::from_id("07d719df00f349ef8de77d542edf010c"),
+                ]),
+            },
+        };
+        assert!(!format!("{:?}", value).contains("rEaqvJka48mpv"));
+        assert_eq!(
+            value.application_credential.redacted().secret.unwrap(),
+            REDACTED
+        );
+        test::compare(APPLICATION_CREDENTIAL_CREATE, value);
+        test::round_trip::<ApplicationCredentialCreateRoot>(APPLICATION_CREDENTIAL_CREATE);
+    }
+
+    #[test]
+    fn test_application_credential() {
+        let root: ApplicationCredentialRoot = serde_json::from_str(APPLICATION_CREDENTIAL).unwrap();
+        let cred = root.application_credential;
+        assert!(!format!("{:?}", cred).contains("rEaqvJka48mpv"));
+        assert_eq!(cred.secret.as_ref().unwrap(), "rEaqvJka48mpv");
+        assert_eq!(cred.roles[0].name, "Reader");
+        assert!(cred.expires_at.is_some());
+        assert!(cred.allows("compute", "GET", "/v2.1/servers"));
+        assert!(!cred.allows("compute", "POST", "/v2.1/servers"));
+        assert!(!cred.allows("compute", "GET", "/v2.1/servers/abcd"));
+
+        let root = ApplicationCredentialsRoot {
+            application_credentials: vec![cred],
+            links: ResourceLinks::default(),
+        };
+        assert!(root.find(&IdOrName::from_name("monitoring")).is_some());
+        assert!(root
+            .find(&IdOrName::from_id("58d61ff8e6e34accb35874016d1dba8b"))
+            .is_some());
+        assert!(root.find(&IdOrName::from_id("monitoring")).is_none());
+    }
+
+    #[test]
+    fn test_application_credential_unrestricted_rules() {
+        let mut value: serde_json::Value = serde_json::from_str(APPLICATION_CREDENTIAL).unwrap();
+        value["application_credential"]["access_rules"] = serde_json::json!([]);
+        let root: ApplicationCredentialRoot = serde_json::from_value(value.clone()).unwrap();
+        assert!(root
+            .application_credential
+            .allows("image", "DELETE", "/v2/images/1"));
+        let _ = value["application_credential"]
+            .as_object_mut()
+            .unwrap()
+            .remove("access_rules");
+        let root: ApplicationCredentialRoot = serde_json::from_value(value).unwrap();
+        assert!(root
+            .application_credential
+            .allows("image", "DELETE", "/v2/images/1"));
+    }
+
+    #[test]
+    fn test_access_rules() {
+        let root: AccessRulesRoot = serde_json::from_str(ACCESS_RULES).unwrap();
+        let rules = &root.access_rules;
+        assert!(find_access_rule(rules, "compute", "GET", "/v2.1/servers/abcd/ips").is_some());
+        assert!(find_access_rule(rules, "compute", "GET", "/v2.1/servers/ab/cd/ips").is_none());
+        assert!(find_access_rule(rules, "compute", "GET", "/v2.1/servers//ips").is_none());
+        assert_eq!(
+            find_access_rule(rules, "image", "GET", "/v2/images/abcd/file")
+                .unwrap()
+                .id
+                .as_ref()
+                .unwrap(),
+            "4dd1cfe7e5ce4a53ba5a37a70ae0e6c1"
+        );
+        assert!(find_access_rule(rules, "image", "HEAD", "/v2/images/abcd").is_none());
+        assert!(find_access_rule(rules, "compute", "GET", "/v2/images/abcd").is_none());
+    }
+
+    #[test]
+    fn test_path_matches() {
+        for (template, path, expected) in &[
+            ("/v2.1/servers", "/v2.1/servers", true),
+            ("/v2.1/servers", "/v2.1/servers/", false),
+            ("/v2.1/servers", "/v2x1/servers", false),
+            ("/v2.1/servers/*", "/v2.1/servers/abcd", true),
+            ("/v2.1/servers/*", "/v2.1/servers/", false),
+            ("/v2.1/servers/*", "/v2.1/servers/ab/cd", false),
+            ("/v2.1/servers/*/ips", "/v2.1/servers/abcd/ips", true),
+            ("/v2.1/servers/**", "/v2.1/servers/", true),
+            ("/v2.1/servers/**", "/v2.1/servers/ab/cd", true),
+            ("/v2.1/servers/**", "/v2.1/flavors", false),
+            ("/v2.1/servers/***", "/v2.1/servers/ab/cd", true),
+            ("/v2.1/servers/***", "/v2.1/servers/", false),
+            (
+                "/v2.1/servers/{id}/action",
+                "/v2.1/servers/abcd/action",
+                true,
+            ),
+            ("/v2.1/servers/{id}/action", "/v2.1/servers/action", false),
+        ] {
+            assert_eq!(
+                path_matches(template, path),
+                *expected,
+                "{} {}",
+                template,
+                path
+            );
+        }
+    }
+}
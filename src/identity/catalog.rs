@@ -0,0 +1,421 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Service catalog endpoint resolution.
+
+use std::error::Error;
+use std::fmt;
+
+use super::super::service_types;
+use super::{CatalogRecord, CatalogRoot, Endpoint, Interface, Token};
+
+/// Criteria for selecting an endpoint from a service catalog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EndpointFilter {
+    /// Service type to look for, its official type and aliases are also accepted.
+    pub service_type: String,
+    /// Acceptable interfaces from the most to the least preferred.
+    pub interfaces: Vec<Interface>,
+    /// Region ID of the endpoint (any region if `None`).
+    pub region: Option<String>,
+    /// Name of the service (any service of the type if `None`).
+    pub service_name: Option<String>,
+}
+
+/// Error returned when the catalog has no endpoint matching a filter.
+#[derive(Clone, Debug)]
+pub enum CatalogError {
+    /// No service with the requested type and name.
+    ServiceNotFound {
+        /// Filter used for the lookup.
+        filter: EndpointFilter,
+        /// Types of the services present in the catalog.
+        available: Vec<String>,
+    },
+    /// Services were found, but none of their endpoints match the interface and region.
+    EndpointNotFound {
+        /// Filter used for the lookup.
+        filter: EndpointFilter,
+        /// Endpoints of the services with the requested type and name.
+        available: Vec<Endpoint>,
+    },
+}
+
+impl EndpointFilter {
+    /// Create a filter for the public endpoint of a service type in any region.
+    pub fn new<T: Into<String>>(service_type: T) -> EndpointFilter {
+        EndpointFilter {
+            service_type: service_type.into(),
+            interfaces: vec![Interface::Public],
+            region: None,
+            service_name: None,
+        }
+    }
+
+    /// Whether the service record matches the service type (or its alias) and name.
+    pub fn matches_service(&self, record: &CatalogRecord) -> bool {
+        service_types::is_same(&self.service_type, &record.service_type)
+            && match self.service_name {
+                Some(ref name) => record.name.as_ref() == Some(name),
+                None => true,
+            }
+    }
+
+    /// Whether the endpoint is enabled and matches the region and one of the interfaces.
+    #[inline]
+    pub fn matches_endpoint(&self, endpoint: &Endpoint) -> bool {
+        self.matches_endpoint_with(endpoint, &self.interfaces)
+    }
+
+    /// Find the best matching endpoint in the catalog.
+    ///
+    /// Interfaces are tried in the order of preference. For each interface, service types are
+    /// tried in the order returned by `service_types::candidates`: the requested type, then the
+    /// official type, then the aliases. The first matching endpoint is returned in the catalog
+    /// order, so if several services of the same type are present, the service name should be
+    /// provided to get a predictable result.
+    pub fn find<'c>(&self, catalog: &'c [CatalogRecord]) -> Result<&'c Endpoint, CatalogError> {
+        let services = catalog
+            .iter()
+            .filter(|record| self.matches_service(record))
+            .collect::<Vec<_>>();
+        if services.is_empty() {
+            return Err(CatalogError::ServiceNotFound {
+                filter: self.clone(),
+                available: catalog
+                    .iter()
+                    .map(|record| record.service_type.clone())
+                    .collect(),
+            });
+        }
+
+        let candidates = service_types::candidates(&self.service_type);
+        for interface in &self.interfaces {
+            for candidate in &candidates {
+                let found = services
+                    .iter()
+                    .filter(|record| &service_types::normalize(&record.service_type) == candidate)
+                    .flat_map(|record| record.endpoints.iter())
+                    .find(|endpoint| {
+                        self.matches_endpoint_with(endpoint, std::slice::from_ref(interface))
+                    });
+                if let Some(endpoint) = found {
+                    return Ok(endpoint);
+                }
+            }
+        }
+
+        Err(CatalogError::EndpointNotFound {
+            filter: self.clone(),
+            available: services
+                .into_iter()
+                .flat_map(|record| record.endpoints.iter().cloned())
+                .collect(),
+        })
+    }
+
+    fn matches_endpoint_with(&self, endpoint: &Endpoint, interfaces: &[Interface]) -> bool {
+        endpoint.enabled
+            && interfaces.contains(&endpoint.interface)
+            && self.matches_region(endpoint)
+    }
+
+    fn matches_region(&self, endpoint: &Endpoint) -> bool {
+        match self.region {
+            Some(ref region) => endpoint.region_id.as_ref().unwrap_or(&endpoint.region) == region,
+            None => true,
+        }
+    }
+}
+
+impl CatalogRoot {
+    /// Find the best matching endpoint in the catalog.
+    #[inline]
+    pub fn find_endpoint(&self, filter: &EndpointFilter) -> Result<&Endpoint, CatalogError> {
+        filter.find(&self.catalog)
+    }
+}
+
+impl Token {
+    /// Find the best matching endpoint in the catalog of the token.
+    #[inline]
+    pub fn find_endpoint(&self, filter: &EndpointFilter) -> Result<&Endpoint, CatalogError> {
+        filter.find(&self.catalog)
+    }
+}
+
+impl fmt::Display for EndpointFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "service type {}", self.service_type)?;
+        if let Some(ref name) = self.service_name {
+            write!(f, ", service name {}", name)?;
+        }
+        let interfaces = self
+            .interfaces
+            .iter()
+            .map(Interface::as_str)
+            .collect::<Vec<_>>();
+        write!(f, ", interfaces {}", interfaces.join(", "))?;
+        if let Some(ref region) = self.region {
+            write!(f, ", region {}", region)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatalogError::ServiceNotFound { filter, available } => write!(
+                f,
+                "No service matching {} in the catalog, available service types: {}",
+                filter,
+                available.join(", ")
+            ),
+            CatalogError::EndpointNotFound { filter, available } => {
+                write!(
+                    f,
+                    "No endpoint matching {} in the catalog, available endpoints:",
+                    filter
+                )?;
+                for endpoint in available {
+                    write!(
+                        f,
+                        " {} {} ({});",
+                        endpoint.interface, endpoint.url, endpoint.region
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for CatalogError {}
+
+#[cfg(test)]
+mod test {
+    use super::super::{CatalogRoot, Interface};
+    use super::{CatalogError, EndpointFilter};
+
+    const CATALOG: &str = r#"
+{
+    "catalog": [
+        {
+            "endpoints": [
+                {
+                    "region_id": "RegionOne",
+                    "url": "https://one.example.com/volume/v3",
+                    "region": "RegionOne",
+                    "interface": "public",
+                    "id": "1a9d8a6cf1f94d0c91d8dd3f0f8b9d4b"
+                },
+                {
+                    "region_id": "RegionTwo",
+                    "url": "https://two.example.com/volume/v3",
+                    "region": "RegionTwo",
+                    "interface": "public",
+                    "id": "7e5d9e4a7d0d4f5d9d3a1a7c3a3e4f11"
+                },
+                {
+                    "region_id": "RegionTwo",
+                    "url": "http://10.0.0.2/volume/v3",
+                    "region": "RegionTwo",
+                    "interface": "internal",
+                    "id": "0ed2e8a1e6c94f8cb1e9a6b1e0a3e8d2"
+                }
+            ],
+            "type": "block-storage",
+            "id": "4f3ed3c4c8b04a7bb1e1d0ef2a3b0b2a",
+            "name": "cinder"
+        },
+        {
+            "endpoints": [
+                {
+                    "region_id": "RegionOne",
+                    "url": "https://one.example.com/volume/v2",
+                    "region": "RegionOne",
+                    "interface": "public",
+                    "id": "5b0e9a1b4e9c4bb0a7b4f6c2e3d1a0f9"
+                },
+                {
+                    "region_id": "RegionOne",
+                    "url": "http://10.0.0.1/volume/v2",
+                    "region": "RegionOne",
+                    "interface": "internal",
+                    "id": "3c4d5e6f7a8b4c9d0e1f2a3b4c5d6e7f"
+                }
+            ],
+            "type": "volumev2",
+            "id": "8e7d6c5b4a394f2e1d0c9b8a7f6e5d4c",
+            "name": "cinderv2"
+        },
+        {
+            "endpoints": [
+                {
+                    "region_id": "RegionOne",
+                    "url": "https://one.example.com/swift/v1",
+                    "region": "RegionOne",
+                    "interface": "public",
+                    "id": "6c1a7b2e0c9d4b4d8e5d9f3c2b1a0e9d"
+                }
+            ],
+            "type": "object-store",
+            "id": "0b3c2d1e4f5a4b6c8d7e9f0a1b2c3d4e",
+            "name": "swift"
+        },
+        {
+            "endpoints": [
+                {
+                    "region_id": "RegionOne",
+                    "url": "https://one.example.com/ceph/v1",
+                    "region": "RegionOne",
+                    "interface": "public",
+                    "id": "9d8c7b6a5f4e4d3c2b1a0f9e8d7c6b5a"
+                }
+            ],
+            "type": "object_store",
+            "id": "1a2b3c4d5e6f4a7b8c9d0e1f2a3b4c5d",
+            "name": "ceph"
+        }
+    ]
+}"#;
+
+    fn catalog() -> CatalogRoot {
+        serde_json::from_str(CATALOG).unwrap()
+    }
+
+    #[test]
+    fn test_find_public_any_region() {
+        let filter = EndpointFilter::new("block-storage");
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/volume/v3");
+    }
+
+    #[test]
+    fn test_find_region() {
+        let filter = EndpointFilter {
+            region: Some("RegionTwo".to_string()),
+            ..EndpointFilter::new("block-storage")
+        };
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://two.example.com/volume/v3");
+    }
+
+    #[test]
+    fn test_find_interface_preference() {
+        let filter = EndpointFilter {
+            interfaces: vec![Interface::Internal, Interface::Public],
+            region: Some("RegionTwo".to_string()),
+            ..EndpointFilter::new("block-storage")
+        };
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "http://10.0.0.2/volume/v3");
+
+        let filter = EndpointFilter {
+            region: Some("RegionOne".to_string()),
+            service_name: Some("cinder".to_string()),
+            ..filter
+        };
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/volume/v3");
+    }
+
+    #[test]
+    fn test_find_service_name() {
+        let filter = EndpointFilter::new("object-store");
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/swift/v1");
+
+        let filter = EndpointFilter {
+            service_name: Some("ceph".to_string()),
+            ..filter
+        };
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/ceph/v1");
+    }
+
+    #[test]
+    fn test_service_not_found() {
+        let filter = EndpointFilter::new("compute");
+        match catalog().find_endpoint(&filter) {
+            Err(CatalogError::ServiceNotFound { available, .. }) => {
+                assert_eq!(
+                    available,
+                    vec!["block-storage", "volumev2", "object-store", "object_store"]
+                );
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let filter = EndpointFilter {
+            service_name: Some("radosgw".to_string()),
+            ..EndpointFilter::new("object-store")
+        };
+        assert!(matches!(
+            catalog().find_endpoint(&filter),
+            Err(CatalogError::ServiceNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_endpoint_not_found() {
+        let filter = EndpointFilter {
+            interfaces: vec![Interface::Admin],
+            ..EndpointFilter::new("block-storage")
+        };
+        let err = catalog().find_endpoint(&filter).unwrap_err();
+        match err {
+            CatalogError::EndpointNotFound { ref available, .. } => {
+                assert_eq!(available.len(), 5);
+            }
+            ref other => panic!("Unexpected error {:?}", other),
+        }
+        let msg = err.to_string();
+        assert!(msg.contains("interfaces admin"));
+        assert!(msg.contains("http://10.0.0.2/volume/v3"));
+    }
+
+    #[test]
+    fn test_find_alias() {
+        let filter = EndpointFilter::new("volumev2");
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/volume/v2");
+
+        let filter = EndpointFilter::new("volume");
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/volume/v3");
+    }
+
+    #[test]
+    fn test_find_alias_interface_first() {
+        let filter = EndpointFilter {
+            interfaces: vec![Interface::Internal, Interface::Public],
+            region: Some("RegionOne".to_string()),
+            ..EndpointFilter::new("block-storage")
+        };
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "http://10.0.0.1/volume/v2");
+    }
+
+    #[test]
+    fn test_find_underscore() {
+        let filter = EndpointFilter {
+            service_name: Some("ceph".to_string()),
+            ..EndpointFilter::new("object-store")
+        };
+        let endpoint = catalog().find_endpoint(&filter).unwrap().clone();
+        assert_eq!(endpoint.url, "https://one.example.com/ceph/v1");
+    }
+}
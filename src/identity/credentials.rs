@@ -0,0 +1,336 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Credentials JSON structures.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::super::common::ResourceLinks;
+use super::REDACTED;
+
+/// Type of EC2 credentials.
+pub const EC2_TYPE: &str = "ec2";
+
+/// A blob of EC2 credentials (serialized as a JSON string in the credential).
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Ec2Blob {
+    /// Access key.
+    pub access: String,
+    /// Secret key.
+    pub secret: String,
+    /// Trust for trust-scoped EC2 credentials.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub trust_id: Option<String>,
+    /// Application credential for application-credential-scoped EC2 credentials.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub app_cred_id: Option<String>,
+    /// OAuth1 access token for OAuth-scoped EC2 credentials.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub access_token_id: Option<String>,
+}
+
+/// A request to create a credential.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CredentialCreate {
+    /// Credential type, e.g. `ec2`, `totp` or `cert`.
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub user_id: String,
+    /// Serialized credential blob.
+    pub blob: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub project_id: Option<String>,
+}
+
+/// A request to update a credential.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct CredentialUpdate {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub credential_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub blob: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub project_id: Option<String>,
+}
+
+/// A credential creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CredentialCreateRoot {
+    pub credential: CredentialCreate,
+}
+
+/// A credential update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CredentialUpdateRoot {
+    pub credential: CredentialUpdate,
+}
+
+/// A credential.
+#[derive(Clone, Deserialize)]
+pub struct Credential {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub user_id: String,
+    /// Serialized credential blob.
+    pub blob: String,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A credential response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CredentialRoot {
+    pub credential: Credential,
+}
+
+/// A list of credentials.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CredentialsRoot {
+    pub credentials: Vec<Credential>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing credentials.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CredentialFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub credential_type: Option<String>,
+}
+
+impl Ec2Blob {
+    /// EC2 credentials with an access and a secret key.
+    pub fn new<S1, S2>(access: S1, secret: S2) -> Ec2Blob
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Ec2Blob {
+            access: access.into(),
+            secret: secret.into(),
+            trust_id: None,
+            app_cred_id: None,
+            access_token_id: None,
+        }
+    }
+
+    /// A copy with the secret replaced by a placeholder.
+    pub fn redacted(&self) -> Ec2Blob {
+        Ec2Blob {
+            secret: REDACTED.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for Ec2Blob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ec2Blob")
+            .field("access", &self.access)
+            .field("secret", &REDACTED)
+            .field("trust_id", &self.trust_id)
+            .field("app_cred_id", &self.app_cred_id)
+            .field("access_token_id", &self.access_token_id)
+            .finish()
+    }
+}
+
+impl CredentialCreate {
+    /// A request to create EC2 credentials.
+    pub fn ec2<S: Into<String>>(user_id: S, project_id: Option<String>, blob: &Ec2Blob) -> Self {
+        CredentialCreate {
+            credential_type: EC2_TYPE.to_string(),
+            user_id: user_id.into(),
+            blob: serde_json::to_string(blob).expect("EC2 blob is always serializable"),
+            project_id,
+        }
+    }
+
+    /// A copy with the blob replaced by a placeholder.
+    pub fn redacted(&self) -> CredentialCreate {
+        CredentialCreate {
+            blob: REDACTED.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for CredentialCreate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CredentialCreate")
+            .field("credential_type", &self.credential_type)
+            .field("user_id", &self.user_id)
+            .field("blob", &REDACTED)
+            .field("project_id", &self.project_id)
+            .finish()
+    }
+}
+
+impl CredentialUpdate {
+    /// A copy with the blob replaced by a placeholder.
+    pub fn redacted(&self) -> CredentialUpdate {
+        CredentialUpdate {
+            blob: self.blob.as_ref().map(|_| REDACTED.to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for CredentialUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CredentialUpdate")
+            .field("credential_type", &self.credential_type)
+            .field("user_id", &self.user_id)
+            .field("blob", &self.blob.as_ref().map(|_| REDACTED))
+            .field("project_id", &self.project_id)
+            .finish()
+    }
+}
+
+impl Credential {
+    /// Whether these are EC2 credentials.
+    #[inline]
+    pub fn is_ec2(&self) -> bool {
+        self.credential_type == EC2_TYPE
+    }
+
+    /// Parse the blob of EC2 credentials.
+    ///
+    /// Returns `None` for credentials of other types.
+    pub fn ec2_blob(&self) -> Option<Result<Ec2Blob, serde_json::Error>> {
+        if self.is_ec2() {
+            Some(serde_json::from_str(&self.blob))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for Credential {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credential")
+            .field("id", &self.id)
+            .field("credential_type", &self.credential_type)
+            .field("user_id", &self.user_id)
+            .field("blob", &REDACTED)
+            .field("project_id", &self.project_id)
+            .field("links", &self.links)
+            .finish()
+    }
+}
+
+impl CredentialsRoot {
+    /// Find EC2 credentials by their access key.
+    pub fn find_ec2(&self, access: &str) -> Option<(&Credential, Ec2Blob)> {
+        self.credentials
+            .iter()
+            .find_map(|cred| match cred.ec2_blob() {
+                Some(Ok(blob)) if blob.access == access => Some((cred, blob)),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::*;
+
+    const CREDENTIAL_CREATE: &str = r#"
+{
+    "credential": {
+        "type": "ec2",
+        "user_id": "bb5476fd12884539b41d5a88f838d773",
+        "blob": "{\"access\":\"181920\",\"secret\":\"secretKey\",\"trust_id\":\"7e8a1c\"}",
+        "project_id": "731fc6f265cd486d900f16e84c5cb594"
+    }
+}"#;
+
+    const CREDENTIALS: &str = r#"
+{
+    "credentials": [
+        {
+            "user_id": "bb5476fd12884539b41d5a88f838d773",
+            "links": {
+                "self": "http://example.com/identity/v3/credentials/207e9b76935efc03804d3dd6ab52d22e9b22a0711e4ada4ff8b76165a07311d7"
+            },
+            "blob": "{\"access\": \"181920\", \"secret\": \"secretKey\", \"trust_id\": null}",
+            "project_id": "731fc6f265cd486d900f16e84c5cb594",
+            "type": "ec2",
+            "id": "207e9b76935efc03804d3dd6ab52d22e9b22a0711e4ada4ff8b76165a07311d7"
+        },
+        {
+            "user_id": "6f556708d04b4ea6bc72d7df2296b71a",
+            "links": {
+                "self": "http://example.com/identity/v3/credentials/2441494e52ab6d594a34d74586075cb299489bdd1e9389e3ab06467a4f460609"
+            },
+            "blob": "{\"foo\": \"bar\"}",
+            "project_id": "1a1d14690f3c4ec5bf5f321c5fde3c16",
+            "type": "cert",
+            "id": "2441494e52ab6d594a34d74586075cb299489bdd1e9389e3ab06467a4f460609"
+        }
+    ],
+    "links": {
+        "self": "http://example.com/identity/v3/credentials",
+        "previous": null,
+        "next": null
+    }
+}"#;
+
+    #[test]
+    fn test_credential_create_ec2() {
+        let blob = Ec2Blob {
+            trust_id: Some("7e8a1c".to_string()),
+            ..Ec2Blob::new("181920", "secretKey")
+        };
+        let value = CredentialCreateRoot {
+            credential: CredentialCreate::ec2(
+                "bb5476fd12884539b41d5a88f838d773",
+                Some("731fc6f265cd486d900f16e84c5cb594".to_string()),
+                &blob,
+            ),
+        };
+        test::compare(CREDENTIAL_CREATE, &value);
+        assert!(!format!("{:?}", value).contains("secretKey"));
+        assert!(!format!("{:?}", blob).contains("secretKey"));
+        assert_eq!(value.credential.redacted().blob, REDACTED);
+    }
+
+    #[test]
+    fn test_credentials() {
+        let root: CredentialsRoot = serde_json::from_str(CREDENTIALS).unwrap();
+        assert_eq!(root.credentials.len(), 2);
+        assert!(!format!("{:?}", root).contains("secretKey"));
+
+        let blob = root.credentials[0].ec2_blob().unwrap().unwrap();
+        assert_eq!(blob.access, "181920");
+        assert_eq!(blob.secret, "secretKey");
+        assert!(blob.trust_id.is_none());
+        assert!(root.credentials[1].ec2_blob().is_none());
+
+        let (cred, blob) = root.find_ec2("181920").unwrap();
+        assert_eq!(cred.user_id, "bb5476fd12884539b41d5a88f838d773");
+        assert_eq!(blob.secret, "secretKey");
+        assert!(root.find_ec2("foo").is_none());
+    }
+}
@@ -0,0 +1,714 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Services, endpoints, regions and endpoint groups JSON structures.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::super::common::ResourceLinks;
+use super::{CatalogRecord, IdOrName, Interface};
+
+/// Path prefix of the endpoint filtering extension.
+pub const EP_FILTER_PATH: &str = "OS-EP-FILTER";
+
+/// A request to create a service.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServiceCreate {
+    #[serde(rename = "type")]
+    pub service_type: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+}
+
+/// A request to update a service.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ServiceUpdate {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub service_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+}
+
+/// A service creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServiceCreateRoot {
+    pub service: ServiceCreate,
+}
+
+/// A service update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServiceUpdateRoot {
+    pub service: ServiceUpdate,
+}
+
+/// A service.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Service {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub service_type: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "super::default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A service response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceRoot {
+    pub service: Service,
+}
+
+/// A list of services.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServicesRoot {
+    pub services: Vec<Service>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing services.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ServiceFilter {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub service_type: Option<String>,
+}
+
+/// A request to create an endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointCreate {
+    pub service_id: String,
+    pub interface: Interface,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+}
+
+/// A request to update an endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct EndpointUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub interface: Option<Interface>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+}
+
+/// An endpoint creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointCreateRoot {
+    pub endpoint: EndpointCreate,
+}
+
+/// An endpoint update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointUpdateRoot {
+    pub endpoint: EndpointUpdate,
+}
+
+/// An endpoint of a service.
+///
+/// Unlike `identity::Endpoint` from the catalog, it refers to its service by ID.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Endpoint {
+    pub id: String,
+    pub service_id: String,
+    pub interface: Interface,
+    pub url: String,
+    #[serde(default)]
+    pub region_id: Option<String>,
+    #[serde(default = "super::default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// An endpoint response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointRoot {
+    pub endpoint: Endpoint,
+}
+
+/// A list of endpoints.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointsRoot {
+    pub endpoints: Vec<Endpoint>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing endpoints.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EndpointListFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interface: Option<Interface>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_id: Option<String>,
+}
+
+/// A request to create a region.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RegionCreate {
+    /// Region ID (generated if not provided on creation).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent_region_id: Option<String>,
+}
+
+/// A region creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegionCreateRoot {
+    pub region: RegionCreate,
+}
+
+/// A request to update a region.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RegionUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent_region_id: Option<String>,
+}
+
+/// A region update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegionUpdateRoot {
+    pub region: RegionUpdate,
+}
+
+/// A region.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Region {
+    pub id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parent_region_id: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A region response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegionRoot {
+    pub region: Region,
+}
+
+/// A list of regions.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegionsRoot {
+    pub regions: Vec<Region>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing regions.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RegionFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_region_id: Option<String>,
+}
+
+/// Endpoint attributes selecting the endpoints of an endpoint group.
+///
+/// Attributes that are not set match any endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct EndpointGroupFilters {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub interface: Option<Interface>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region_id: Option<String>,
+}
+
+/// A request to create an endpoint group.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointGroupCreate {
+    pub name: String,
+    pub filters: EndpointGroupFilters,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A request to update an endpoint group.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct EndpointGroupUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub filters: Option<EndpointGroupFilters>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// An endpoint group creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointGroupCreateRoot {
+    pub endpoint_group: EndpointGroupCreate,
+}
+
+/// An endpoint group update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EndpointGroupUpdateRoot {
+    pub endpoint_group: EndpointGroupUpdate,
+}
+
+/// An endpoint group.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub filters: EndpointGroupFilters,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// An endpoint group response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointGroupRoot {
+    pub endpoint_group: EndpointGroup,
+}
+
+/// A list of endpoint groups.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointGroupsRoot {
+    pub endpoint_groups: Vec<EndpointGroup>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Endpoints associated with a project via `OS-EP-FILTER`.
+///
+/// Built from `OS-EP-FILTER/projects/{project_id}/endpoints` and
+/// `OS-EP-FILTER/projects/{project_id}/endpoint_groups`.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectEndpoints {
+    /// IDs of directly associated endpoints.
+    pub endpoint_ids: Vec<String>,
+    /// Associated endpoint groups.
+    pub endpoint_groups: Vec<EndpointGroupFilters>,
+}
+
+impl ServicesRoot {
+    /// Find a service by its ID or name.
+    pub fn find(&self, reference: &IdOrName) -> Option<&Service> {
+        self.services.iter().find(|service| match reference {
+            IdOrName::Id(ref id) => service.id == *id,
+            IdOrName::Name(ref name) => service.name.as_ref() == Some(name),
+        })
+    }
+}
+
+impl RegionsRoot {
+    /// Find a region by its ID.
+    pub fn find(&self, id: &str) -> Option<&Region> {
+        self.regions.iter().find(|region| region.id == id)
+    }
+
+    /// Parent regions of a region, starting with the direct parent.
+    ///
+    /// Parents missing from the list and cycles end the chain.
+    pub fn parents(&self, id: &str) -> Vec<&Region> {
+        let mut seen = HashSet::new();
+        let _ = seen.insert(id);
+        let mut result = Vec::new();
+        let mut current = self.find(id);
+        while let Some(parent_id) = current.and_then(|region| region.parent_region_id.as_ref()) {
+            if !seen.insert(parent_id.as_str()) {
+                break;
+            }
+            current = self.find(parent_id);
+            if let Some(parent) = current {
+                result.push(parent);
+            }
+        }
+        result
+    }
+
+    /// Whether the region is the same as or a descendant of the ancestor.
+    pub fn is_within(&self, id: &str, ancestor_id: &str) -> bool {
+        id == ancestor_id
+            || self
+                .parents(id)
+                .into_iter()
+                .any(|region| region.id == ancestor_id)
+    }
+}
+
+impl EndpointGroupFilters {
+    /// Whether the endpoint matches all set attributes.
+    pub fn matches(&self, endpoint: &Endpoint) -> bool {
+        self.service_id
+            .as_ref()
+            .is_none_or(|service_id| &endpoint.service_id == service_id)
+            && self
+                .interface
+                .as_ref()
+                .is_none_or(|interface| &endpoint.interface == interface)
+            && self
+                .region_id
+                .as_ref()
+                .is_none_or(|region_id| endpoint.region_id.as_ref() == Some(region_id))
+    }
+}
+
+impl EndpointGroupsRoot {
+    /// Find an endpoint group by its ID or name.
+    pub fn find(&self, reference: &IdOrName) -> Option<&EndpointGroup> {
+        self.endpoint_groups.iter().find(|group| match reference {
+            IdOrName::Id(ref id) => group.id == *id,
+            IdOrName::Name(ref name) => group.name == *name,
+        })
+    }
+}
+
+impl ProjectEndpoints {
+    /// Whether the project has no associations (and thus sees all endpoints).
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.endpoint_ids.is_empty() && self.endpoint_groups.is_empty()
+    }
+
+    /// Whether the endpoint is visible to the project.
+    pub fn allows(&self, endpoint: &Endpoint) -> bool {
+        self.is_empty()
+            || self.endpoint_ids.contains(&endpoint.id)
+            || self
+                .endpoint_groups
+                .iter()
+                .any(|group| group.matches(endpoint))
+    }
+}
+
+/// Render the catalog a project would see.
+///
+/// Disabled services and endpoints are skipped, as are services without visible endpoints. If
+/// the project has no associations, all endpoints are visible (the Keystone default).
+pub fn effective_catalog(
+    services: &[Service],
+    endpoints: &[Endpoint],
+    associations: &ProjectEndpoints,
+) -> Vec<CatalogRecord> {
+    services
+        .iter()
+        .filter(|service| service.enabled)
+        .filter_map(|service| {
+            let visible = endpoints
+                .iter()
+                .filter(|endpoint| {
+                    endpoint.enabled
+                        && endpoint.service_id == service.id
+                        && associations.allows(endpoint)
+                })
+                .map(|endpoint| super::Endpoint {
+                    id: Some(endpoint.id.clone()),
+                    interface: endpoint.interface.clone(),
+                    region: endpoint.region_id.clone().unwrap_or_default(),
+                    region_id: endpoint.region_id.clone(),
+                    url: endpoint.url.clone(),
+                    enabled: true,
+                })
+                .collect::<Vec<_>>();
+            if visible.is_empty() {
+                None
+            } else {
+                Some(CatalogRecord {
+                    id: Some(service.id.clone()),
+                    service_type: service.service_type.clone(),
+                    name: service.name.clone(),
+                    endpoints: visible,
+                })
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::super::EndpointFilter;
+    use super::*;
+
+    const ENDPOINT_CREATE: &str = r#"
+{
+    "endpoint": {
+        "service_id": "9242e05f0c23467bbd1cf1f7a6e5e596",
+        "interface": "public",
+        "url": "http://example.com/identity/v3/endpoints/828384",
+        "region_id": "RegionOne",
+        "enabled": true
+    }
+}"#;
+
+    const ENDPOINT_GROUP_CREATE: &str = r#"
+{
+    "endpoint_group": {
+        "name": "internal",
+        "filters": {
+            "interface": "internal"
+        },
+        "description": "All internal endpoints"
+    }
+}"#;
+
+    const SERVICES: &str = r#"
+{
+    "links": {
+        "next": null,
+        "previous": null,
+        "self": "http://example.com/identity/v3/services"
+    },
+    "services": [
+        {
+            "description": "Keystone Identity Service",
+            "enabled": true,
+            "id": "686766",
+            "links": {
+                "self": "http://example.com/identity/v3/services/686766"
+            },
+            "name": "keystone",
+            "type": "identity"
+        },
+        {
+            "enabled": true,
+            "id": "1999c3",
+            "name": "nova",
+            "type": "compute"
+        },
+        {
+            "enabled": false,
+            "id": "disabled",
+            "name": "cinder",
+            "type": "block-storage"
+        }
+    ]
+}"#;
+
+    const ENDPOINTS: &str = r#"
+{
+    "endpoints": [
+        {
+            "enabled": true,
+            "id": "public-identity",
+            "interface": "public",
+            "region_id": "RegionOne",
+            "service_id": "686766",
+            "url": "http://example.com/identity"
+        },
+        {
+            "enabled": true,
+            "id": "internal-identity",
+            "interface": "internal",
+            "region_id": "RegionOne",
+            "service_id": "686766",
+            "url": "http://10.0.0.1/identity"
+        },
+        {
+            "enabled": true,
+            "id": "public-compute",
+            "interface": "public",
+            "region_id": "RegionTwo",
+            "service_id": "1999c3",
+            "url": "http://example.com/compute/v2.1"
+        },
+        {
+            "enabled": false,
+            "id": "disabled-compute",
+            "interface": "admin",
+            "region_id": "RegionOne",
+            "service_id": "1999c3",
+            "url": "http://10.0.0.1/compute/v2.1"
+        },
+        {
+            "id": "public-volume",
+            "interface": "public",
+            "service_id": "disabled",
+            "url": "http://example.com/volume/v3"
+        }
+    ],
+    "links": {
+        "self": "http://example.com/identity/v3/endpoints",
+        "previous": null,
+        "next": null
+    }
+}"#;
+
+    const REGIONS: &str = r#"
+{
+    "links": {
+        "next": null,
+        "previous": null,
+        "self": "http://example.com/identity/v3/regions"
+    },
+    "regions": [
+        {
+            "description": "",
+            "id": "RegionOne",
+            "links": {
+                "self": "http://example.com/identity/v3/regions/RegionOne"
+            },
+            "parent_region_id": "Europe"
+        },
+        {
+            "id": "Europe",
+            "parent_region_id": "World"
+        },
+        {
+            "id": "World",
+            "parent_region_id": null
+        },
+        {
+            "id": "Loop",
+            "parent_region_id": "Loop"
+        }
+    ]
+}"#;
+
+    fn fixtures() -> (Vec<Service>, Vec<Endpoint>) {
+        let services: ServicesRoot = serde_json::from_str(SERVICES).unwrap();
+        let endpoints: EndpointsRoot = serde_json::from_str(ENDPOINTS).unwrap();
+        (services.services, endpoints.endpoints)
+    }
+
+    #[test]
+    fn test_endpoint_create() {
+        let value = EndpointCreateRoot {
+            endpoint: EndpointCreate {
+                service_id: "9242e05f0c23467bbd1cf1f7a6e5e596".to_string(),
+                interface: Interface::Public,
+                url: "http://example.com/identity/v3/endpoints/828384".to_string(),
+                region_id: Some("RegionOne".to_string()),
+                enabled: Some(true),
+            },
+        };
+        test::compare(ENDPOINT_CREATE, &value);
+        test::round_trip::<EndpointCreateRoot>(ENDPOINT_CREATE);
+    }
+
+    #[test]
+    fn test_endpoint_group_create() {
+        let value = EndpointGroupCreateRoot {
+            endpoint_group: EndpointGroupCreate {
+                name: "internal".to_string(),
+                filters: EndpointGroupFilters {
+                    interface: Some(Interface::Internal),
+                    ..Default::default()
+                },
+                description: Some("All internal endpoints".to_string()),
+            },
+        };
+        test::compare(ENDPOINT_GROUP_CREATE, &value);
+        test::round_trip::<EndpointGroupCreateRoot>(ENDPOINT_GROUP_CREATE);
+    }
+
+    #[test]
+    fn test_services() {
+        let root: ServicesRoot = serde_json::from_str(SERVICES).unwrap();
+        assert_eq!(root.services.len(), 3);
+        assert_eq!(
+            root.find(&IdOrName::Name("nova".to_string())).unwrap().id,
+            "1999c3"
+        );
+        assert_eq!(
+            root.find(&IdOrName::Id("686766".to_string()))
+                .unwrap()
+                .service_type,
+            "identity"
+        );
+        assert!(root.find(&IdOrName::Id("nova".to_string())).is_none());
+    }
+
+    #[test]
+    fn test_regions() {
+        let root: RegionsRoot = serde_json::from_str(REGIONS).unwrap();
+        let parents = root
+            .parents("RegionOne")
+            .into_iter()
+            .map(|region| region.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(parents, vec!["Europe", "World"]);
+        assert!(root.parents("World").is_empty());
+        assert!(root.parents("Loop").is_empty());
+        assert!(root.parents("Unknown").is_empty());
+        assert!(root.is_within("RegionOne", "World"));
+        assert!(root.is_within("Europe", "Europe"));
+        assert!(!root.is_within("World", "Europe"));
+    }
+
+    #[test]
+    fn test_effective_catalog_all() {
+        let (services, endpoints) = fixtures();
+        let catalog = effective_catalog(&services, &endpoints, &ProjectEndpoints::default());
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog[0].service_type, "identity");
+        assert_eq!(catalog[0].endpoints.len(), 2);
+        assert_eq!(catalog[1].service_type, "compute");
+        assert_eq!(catalog[1].endpoints.len(), 1);
+        assert_eq!(catalog[1].endpoints[0].region, "RegionTwo");
+
+        let endpoint = EndpointFilter::new("compute").find(&catalog).unwrap();
+        assert_eq!(endpoint.url, "http://example.com/compute/v2.1");
+    }
+
+    #[test]
+    fn test_effective_catalog_filtered() {
+        let (services, endpoints) = fixtures();
+        let associations = ProjectEndpoints {
+            endpoint_ids: vec!["public-compute".to_string(), "public-volume".to_string()],
+            endpoint_groups: vec![EndpointGroupFilters {
+                service_id: Some("686766".to_string()),
+                interface: Some(Interface::Internal),
+                region_id: None,
+            }],
+        };
+        let catalog = effective_catalog(&services, &endpoints, &associations);
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog[0].endpoints.len(), 1);
+        assert_eq!(catalog[0].endpoints[0].url, "http://10.0.0.1/identity");
+        assert_eq!(
+            catalog[1].endpoints[0].id.as_ref().unwrap(),
+            "public-compute"
+        );
+    }
+}
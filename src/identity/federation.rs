@@ -0,0 +1,1116 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! OS-FEDERATION JSON structures and a local mapping engine.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use super::super::common::{IdRef, ResourceLinks};
+use super::{AuthRoot, IdOrName, Scope};
+
+/// A request to create or update an identity provider.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct IdentityProviderCreate {
+    /// Domain for federated users (created if missing on creation).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    /// Entity IDs of the identity provider (e.g. SAML `entityID`).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub remote_ids: Option<Vec<String>>,
+    /// Time in minutes for which group memberships from the assertion are kept.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub authorization_ttl: Option<u32>,
+}
+
+/// An identity provider creation or update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IdentityProviderCreateRoot {
+    pub identity_provider: IdentityProviderCreate,
+}
+
+/// An identity provider.
+#[derive(Clone, Debug, Deserialize)]
+pub struct IdentityProvider {
+    pub id: String,
+    pub domain_id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub remote_ids: Vec<String>,
+    #[serde(default)]
+    pub authorization_ttl: Option<u32>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// An identity provider response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct IdentityProviderRoot {
+    pub identity_provider: IdentityProvider,
+}
+
+/// A list of identity providers.
+#[derive(Clone, Debug, Deserialize)]
+pub struct IdentityProvidersRoot {
+    pub identity_providers: Vec<IdentityProvider>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A request to create or update a protocol of an identity provider.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProtocolCreate {
+    pub mapping_id: String,
+    /// Attribute containing the remote ID of the identity provider.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub remote_id_attribute: Option<String>,
+}
+
+/// A protocol creation or update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProtocolCreateRoot {
+    pub protocol: ProtocolCreate,
+}
+
+/// A protocol of an identity provider.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Protocol {
+    /// Protocol name, e.g. `saml2`, `openid` or `mapped`.
+    pub id: String,
+    pub mapping_id: String,
+    #[serde(default)]
+    pub remote_id_attribute: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A protocol response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProtocolRoot {
+    pub protocol: Protocol,
+}
+
+/// A list of protocols.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProtocolsRoot {
+    pub protocols: Vec<Protocol>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A request to create or update a mapping.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MappingCreate {
+    pub rules: Vec<MappingRule>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub schema_version: Option<String>,
+}
+
+/// A mapping creation or update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MappingCreateRoot {
+    pub mapping: MappingCreate,
+}
+
+/// A mapping.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Mapping {
+    pub id: String,
+    pub rules: Vec<MappingRule>,
+    #[serde(default)]
+    pub schema_version: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A mapping response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MappingRoot {
+    pub mapping: Mapping,
+}
+
+/// A list of mappings.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MappingsRoot {
+    pub mappings: Vec<Mapping>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A mapping rule.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MappingRule {
+    /// Local properties, `{N}` is replaced with the values of the N-th direct mapping.
+    pub local: Vec<LocalRule>,
+    /// Requirements to the remote attributes.
+    pub remote: Vec<RemoteRule>,
+}
+
+/// A requirement to a remote attribute.
+///
+/// Requirements without `any_one_of` and `not_any_of` produce direct mappings used in `{N}`
+/// substitutions (optionally filtered by `blacklist` or `whitelist`).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RemoteRule {
+    /// Name of the remote attribute.
+    #[serde(rename = "type")]
+    pub attribute: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub any_one_of: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub not_any_of: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub blacklist: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub whitelist: Option<Vec<String>>,
+    /// Whether values are regular expressions.
+    #[serde(skip_serializing_if = "super::is_false", default)]
+    pub regex: bool,
+}
+
+/// Local properties produced by a mapping rule.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LocalRule {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user: Option<LocalUser>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub group: Option<LocalGroup>,
+    /// Group names, either a single name or a list (usually from a direct mapping).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub groups: Option<String>,
+    /// Group IDs, either a single ID or a list (usually from a direct mapping).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub group_ids: Option<String>,
+    /// Domain of `groups`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<IdOrName>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub projects: Option<Vec<LocalProject>>,
+}
+
+/// A user in a mapping rule.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LocalUser {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<IdOrName>,
+    /// Either `ephemeral` (the default) or `local`.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub user_type: Option<String>,
+}
+
+/// A group in a mapping rule, referenced by ID or by name and domain.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LocalGroup {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<IdOrName>,
+}
+
+/// A project to create for a federated user.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LocalProject {
+    pub name: String,
+    #[serde(default)]
+    pub roles: Vec<LocalRole>,
+}
+
+/// A role on a project for a federated user.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LocalRole {
+    pub name: String,
+}
+
+/// A group referenced by name in a mapping result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MappedGroup {
+    pub name: String,
+    pub domain: IdOrName,
+}
+
+/// Result of applying a mapping to an assertion.
+#[derive(Clone, Debug, Default)]
+pub struct MappedProperties {
+    /// The user from the first matching rule that provides one.
+    ///
+    /// The type defaults to `ephemeral`, the name defaults to the ID.
+    pub user: Option<LocalUser>,
+    pub group_ids: Vec<String>,
+    pub group_names: Vec<MappedGroup>,
+    pub projects: Vec<LocalProject>,
+    /// Indexes of the rules that matched the assertion.
+    pub matched_rules: Vec<usize>,
+}
+
+/// An error when applying a mapping.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MappingError {
+    /// A regular expression in a rule is not valid.
+    InvalidRegex { pattern: String, message: String },
+    /// A `{N}` substitution refers to a missing direct mapping.
+    MissingDirectMapping { rule: usize, index: usize },
+    /// A group is referenced by name without a domain.
+    MissingGroupDomain { rule: usize, group: String },
+    /// A local rule cannot be processed.
+    InvalidLocalRule { rule: usize, message: String },
+}
+
+/// A request to create or update a service provider.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ServiceProviderCreate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub auth_url: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sp_url: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub relay_state_prefix: Option<String>,
+}
+
+/// A service provider creation or update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServiceProviderCreateRoot {
+    pub service_provider: ServiceProviderCreate,
+}
+
+/// A service provider (a remote Keystone for Keystone-to-Keystone federation).
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceProvider {
+    pub id: String,
+    /// Federated authentication URL of the remote Keystone.
+    pub auth_url: Url,
+    /// URL accepting SAML assertions (ECP endpoint).
+    pub sp_url: Url,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub relay_state_prefix: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A service provider response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceProviderRoot {
+    pub service_provider: ServiceProvider,
+}
+
+/// A list of service providers.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceProvidersRoot {
+    pub service_providers: Vec<ServiceProvider>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Path (relative to the identity endpoint) to request an ECP-wrapped SAML assertion.
+pub const ECP_PATH: &str = "auth/OS-FEDERATION/saml2/ecp";
+
+/// Path (relative to the identity endpoint) to request a bare SAML assertion.
+pub const SAML2_PATH: &str = "auth/OS-FEDERATION/saml2";
+
+/// Content type used when posting an ECP assertion to a service provider.
+pub const PAOS_CONTENT_TYPE: &str = "application/vnd.paos+xml";
+
+impl ServiceProvider {
+    /// An authentication request for a SAML assertion for this service provider.
+    ///
+    /// The request should be posted to `ECP_PATH` (or `SAML2_PATH`) of the local Keystone, the
+    /// resulting assertion is then posted to `sp_url` with `PAOS_CONTENT_TYPE`.
+    pub fn assertion_request<S: Into<String>>(&self, token: S) -> AuthRoot {
+        AuthRoot::rescope(
+            token,
+            Scope::ServiceProvider(IdRef {
+                id: self.id.clone(),
+            }),
+        )
+    }
+}
+
+impl Mapping {
+    /// Apply the mapping rules to an assertion, see `apply_mapping`.
+    pub fn apply<I, K, V>(&self, assertion: I) -> Result<MappedProperties, MappingError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: AsRef<str>,
+    {
+        apply_mapping(&self.rules, assertion)
+    }
+}
+
+/// Apply mapping rules to an assertion like Keystone does.
+///
+/// Assertion values may contain several values separated by `;`. Every rule whose remote
+/// requirements are satisfied contributes its local properties. If no rule matches, the result
+/// is empty (Keystone rejects such authentication attempts).
+pub fn apply_mapping<I, K, V>(
+    rules: &[MappingRule],
+    assertion: I,
+) -> Result<MappedProperties, MappingError>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: AsRef<str>,
+{
+    let assertion = assertion
+        .into_iter()
+        .map(|(key, value)| {
+            let values = value.as_ref().split(';').map(String::from).collect();
+            (key.into(), values)
+        })
+        .collect::<HashMap<String, Vec<String>>>();
+
+    let mut result = MappedProperties::default();
+    let mut group_names: BTreeMap<(String, String), IdOrName> = BTreeMap::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let direct_maps = match verify_remote(&rule.remote, &assertion)? {
+            Some(direct_maps) => direct_maps,
+            None => continue,
+        };
+        result.matched_rules.push(index);
+
+        for local in &rule.local {
+            let local = substitute_local(local, &direct_maps, index)?;
+            if let Some(user) = local.user {
+                if result.user.is_none() {
+                    result.user = Some(user);
+                }
+            }
+            if let Some(group) = local.group {
+                match (group.id, group.name, group.domain) {
+                    (Some(id), _, _) => push_unique(&mut result.group_ids, id),
+                    (None, Some(name), Some(domain)) => {
+                        let _ = group_names.insert((domain_key(&domain), name), domain);
+                    }
+                    (None, Some(name), None) => {
+                        return Err(MappingError::MissingGroupDomain {
+                            rule: index,
+                            group: name,
+                        })
+                    }
+                    (None, None, _) => {
+                        return Err(MappingError::InvalidLocalRule {
+                            rule: index,
+                            message: "group requires an id or a name".to_string(),
+                        })
+                    }
+                }
+            }
+            if let Some(groups) = local.groups {
+                let names = parse_list(&groups);
+                match local.domain {
+                    Some(domain) => {
+                        for name in names {
+                            let _ = group_names.insert((domain_key(&domain), name), domain.clone());
+                        }
+                    }
+                    None => {
+                        return Err(MappingError::MissingGroupDomain {
+                            rule: index,
+                            group: groups,
+                        })
+                    }
+                }
+            }
+            if let Some(group_ids) = local.group_ids {
+                for id in parse_list(&group_ids) {
+                    push_unique(&mut result.group_ids, id);
+                }
+            }
+            if let Some(projects) = local.projects {
+                result.projects = projects;
+            }
+        }
+    }
+
+    result.group_names = group_names
+        .into_iter()
+        .map(|((_, name), domain)| MappedGroup { name, domain })
+        .collect();
+    if let Some(ref mut user) = result.user {
+        if user.user_type.is_none() {
+            user.user_type = Some("ephemeral".to_string());
+        }
+        if user.name.is_none() {
+            user.name = user.id.clone();
+        }
+    }
+    Ok(result)
+}
+
+/// Check remote requirements, returning direct mappings if all are satisfied.
+fn verify_remote(
+    requirements: &[RemoteRule],
+    assertion: &HashMap<String, Vec<String>>,
+) -> Result<Option<Vec<Vec<String>>>, MappingError> {
+    let mut direct_maps = Vec::new();
+    for requirement in requirements {
+        let values = match assertion.get(&requirement.attribute) {
+            Some(values) if !values.is_empty() => values,
+            _ => return Ok(None),
+        };
+
+        if let Some(ref expected) = requirement.any_one_of {
+            if matching(expected, values, requirement.regex)?.is_empty() {
+                return Ok(None);
+            }
+            continue;
+        }
+        if let Some(ref unexpected) = requirement.not_any_of {
+            if !matching(unexpected, values, requirement.regex)?.is_empty() {
+                return Ok(None);
+            }
+            continue;
+        }
+
+        let values = if let Some(ref blacklist) = requirement.blacklist {
+            let excluded = matching(blacklist, values, requirement.regex)?;
+            values
+                .iter()
+                .filter(|value| !excluded.contains(value))
+                .cloned()
+                .collect()
+        } else if let Some(ref whitelist) = requirement.whitelist {
+            matching(whitelist, values, requirement.regex)?
+                .into_iter()
+                .cloned()
+                .collect()
+        } else {
+            values.clone()
+        };
+        direct_maps.push(values);
+    }
+    Ok(Some(direct_maps))
+}
+
+/// Assertion values matching any of the patterns.
+fn matching<'a>(
+    patterns: &[String],
+    values: &'a [String],
+    regex: bool,
+) -> Result<Vec<&'a String>, MappingError> {
+    if regex {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|err| MappingError::InvalidRegex {
+                    pattern: pattern.clone(),
+                    message: err.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values
+            .iter()
+            .filter(|value| patterns.iter().any(|pattern| pattern.is_match(value)))
+            .collect())
+    } else {
+        Ok(values
+            .iter()
+            .filter(|value| patterns.contains(value))
+            .collect())
+    }
+}
+
+/// Replace `{N}` in all strings of a local rule.
+fn substitute_local(
+    local: &LocalRule,
+    direct_maps: &[Vec<String>],
+    rule: usize,
+) -> Result<LocalRule, MappingError> {
+    let invalid = |err: serde_json::Error| MappingError::InvalidLocalRule {
+        rule,
+        message: err.to_string(),
+    };
+    let mut value = serde_json::to_value(local).map_err(invalid)?;
+    substitute_value(&mut value, direct_maps, rule)?;
+    serde_json::from_value(value).map_err(invalid)
+}
+
+fn substitute_value(
+    value: &mut Value,
+    direct_maps: &[Vec<String>],
+    rule: usize,
+) -> Result<(), MappingError> {
+    match value {
+        Value::String(ref mut text) => *text = format_direct_maps(text, direct_maps, rule)?,
+        Value::Array(ref mut items) => {
+            for item in items {
+                substitute_value(item, direct_maps, rule)?;
+            }
+        }
+        Value::Object(ref mut map) => {
+            for item in map.values_mut() {
+                substitute_value(item, direct_maps, rule)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Python-style `str.format` with positional arguments.
+///
+/// A single value is inserted as it is, several values - as a Python list.
+fn format_direct_maps(
+    template: &str,
+    direct_maps: &[Vec<String>],
+    rule: usize,
+) -> Result<String, MappingError> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                let _ = chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                let _ = chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut index = String::new();
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                    index.push(c);
+                }
+                let index = index
+                    .parse::<usize>()
+                    .map_err(|_| MappingError::InvalidLocalRule {
+                        rule,
+                        message: format!("invalid substitution in {}", template),
+                    })?;
+                match direct_maps.get(index) {
+                    Some(values) if values.len() == 1 => result.push_str(&values[0]),
+                    Some(values) => {
+                        let quoted = values
+                            .iter()
+                            .map(|value| format!("'{}'", value))
+                            .collect::<Vec<_>>();
+                        result.push('[');
+                        result.push_str(&quoted.join(", "));
+                        result.push(']');
+                    }
+                    None => return Err(MappingError::MissingDirectMapping { rule, index }),
+                }
+            }
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/// Parse a Python list of strings or return the value as a single item.
+fn parse_list(value: &str) -> Vec<String> {
+    match value
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+    {
+        Some(inner) if inner.trim().is_empty() => Vec::new(),
+        Some(inner) => inner
+            .split(',')
+            .map(|item| {
+                item.trim()
+                    .trim_matches(|c| c == '\'' || c == '"')
+                    .to_string()
+            })
+            .collect(),
+        None => vec![value.to_string()],
+    }
+}
+
+fn push_unique(items: &mut Vec<String>, item: String) {
+    if !items.contains(&item) {
+        items.push(item);
+    }
+}
+
+fn domain_key(domain: &IdOrName) -> String {
+    match domain {
+        IdOrName::Id(ref id) => format!("id:{}", id),
+        IdOrName::Name(ref name) => format!("name:{}", name),
+    }
+}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MappingError::InvalidRegex {
+                ref pattern,
+                ref message,
+            } => write!(f, "Invalid regular expression {}: {}", pattern, message),
+            MappingError::MissingDirectMapping { rule, index } => write!(
+                f,
+                "Rule {} refers to direct mapping {{{}}} that does not exist",
+                rule, index
+            ),
+            MappingError::MissingGroupDomain { rule, ref group } => {
+                write!(f, "Rule {}: group {} requires a domain", rule, group)
+            }
+            MappingError::InvalidLocalRule { rule, ref message } => {
+                write!(f, "Rule {}: {}", rule, message)
+            }
+        }
+    }
+}
+
+impl Error for MappingError {}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::*;
+
+    const MAPPING: &str = r#"
+{
+    "mapping": {
+        "id": "ACME",
+        "links": {
+            "self": "http://example.com/identity/v3/OS-FEDERATION/mappings/ACME"
+        },
+        "rules": [
+            {
+                "local": [
+                    {
+                        "user": {
+                            "name": "{0}",
+                            "email": "{1}"
+                        }
+                    },
+                    {
+                        "group": {
+                            "id": "0cd5e9"
+                        }
+                    }
+                ],
+                "remote": [
+                    {
+                        "type": "UserName"
+                    },
+                    {
+                        "type": "Email"
+                    },
+                    {
+                        "type": "orgPersonType",
+                        "not_any_of": [
+                            "Contractor",
+                            "Guest"
+                        ]
+                    }
+                ]
+            },
+            {
+                "local": [
+                    {
+                        "groups": "{0}",
+                        "domain": {
+                            "name": "federated"
+                        }
+                    }
+                ],
+                "remote": [
+                    {
+                        "type": "Groups",
+                        "blacklist": [
+                            "^admin.*"
+                        ],
+                        "regex": true
+                    }
+                ]
+            },
+            {
+                "local": [
+                    {
+                        "group_ids": "{0}"
+                    }
+                ],
+                "remote": [
+                    {
+                        "type": "GroupIds",
+                        "whitelist": [
+                            "abc123",
+                            "def456"
+                        ]
+                    }
+                ]
+            },
+            {
+                "local": [
+                    {
+                        "user": {
+                            "id": "{0}",
+                            "type": "local",
+                            "domain": {
+                                "id": "default"
+                            }
+                        }
+                    },
+                    {
+                        "group": {
+                            "name": "Contractors",
+                            "domain": {
+                                "id": "default"
+                            }
+                        }
+                    },
+                    {
+                        "projects": [
+                            {
+                                "name": "Contractor project for {0}",
+                                "roles": [
+                                    {
+                                        "name": "member"
+                                    }
+                                ]
+                            }
+                        ]
+                    }
+                ],
+                "remote": [
+                    {
+                        "type": "UserName"
+                    },
+                    {
+                        "type": "orgPersonType",
+                        "any_one_of": [
+                            "^Contract.*$"
+                        ],
+                        "regex": true
+                    }
+                ]
+            }
+        ]
+    }
+}"#;
+
+    const IDENTITY_PROVIDER: &str = r#"
+{
+    "identity_provider": {
+        "authorization_ttl": null,
+        "domain_id": "1789d1",
+        "description": "Stores ACME identities",
+        "remote_ids": ["acme_id_1", "acme_id_2"],
+        "enabled": true,
+        "id": "ACME",
+        "links": {
+            "protocols": "http://example.com/identity/v3/OS-FEDERATION/identity_providers/ACME/protocols",
+            "self": "http://example.com/identity/v3/OS-FEDERATION/identity_providers/ACME"
+        }
+    }
+}"#;
+
+    const PROTOCOLS: &str = r#"
+{
+    "links": {
+        "next": null,
+        "previous": null,
+        "self": "http://example.com/identity/v3/OS-FEDERATION/identity_providers/ACME/protocols"
+    },
+    "protocols": [
+        {
+            "id": "saml2",
+            "links": {
+                "identity_provider": "http://example.com/identity/v3/OS-FEDERATION/identity_providers/ACME",
+                "self": "http://example.com/identity/v3/OS-FEDERATION/identity_providers/ACME/protocols/saml2"
+            },
+            "mapping_id": "xyz234"
+        },
+        {
+            "id": "openid",
+            "mapping_id": "oidc-mapping",
+            "remote_id_attribute": "HTTP_OIDC_ISS"
+        }
+    ]
+}"#;
+
+    const SERVICE_PROVIDERS: &str = r#"
+{
+    "links": {
+        "next": null,
+        "previous": null,
+        "self": "http://example.com/identity/v3/OS-FEDERATION/service_providers"
+    },
+    "service_providers": [
+        {
+            "auth_url": "https://other.example.com:5000/v3/OS-FEDERATION/identity_providers/acme/protocols/saml2/auth",
+            "description": "Remote Service Provider",
+            "enabled": true,
+            "id": "ACME",
+            "links": {
+                "self": "https://example.com/identity/v3/OS-FEDERATION/service_providers/ACME"
+            },
+            "relay_state_prefix": "ss:mem:",
+            "sp_url": "https://other.example.com:5000/Shibboleth.sso/SAML2/ECP"
+        }
+    ]
+}"#;
+
+    fn mapping() -> Mapping {
+        serde_json::from_str::<MappingRoot>(MAPPING)
+            .unwrap()
+            .mapping
+    }
+
+    #[test]
+    fn test_mapping_round_trip() {
+        let mapping = mapping();
+        assert_eq!(mapping.rules.len(), 4);
+        assert!(mapping.rules[1].remote[0].regex);
+        let value = MappingCreateRoot {
+            mapping: MappingCreate {
+                rules: mapping.rules,
+                schema_version: None,
+            },
+        };
+        let expected = serde_json::from_str::<Value>(MAPPING).unwrap()["mapping"]["rules"].clone();
+        assert_eq!(
+            serde_json::to_value(&value).unwrap()["mapping"]["rules"],
+            expected
+        );
+    }
+
+    #[test]
+    fn test_apply_employee() {
+        let result = mapping()
+            .apply(vec![
+                ("UserName", "jdoe"),
+                ("Email", "jdoe@example.com"),
+                ("orgPersonType", "Employee;Engineer"),
+                ("Groups", "admins;developers;testers"),
+                ("GroupIds", "abc123;zzz999"),
+            ])
+            .unwrap();
+        assert_eq!(result.matched_rules, vec![0, 1, 2]);
+        assert_eq!(
+            result.user.unwrap(),
+            LocalUser {
+                name: Some("jdoe".to_string()),
+                email: Some("jdoe@example.com".to_string()),
+                user_type: Some("ephemeral".to_string()),
+                ..LocalUser::default()
+            }
+        );
+        assert_eq!(result.group_ids, vec!["0cd5e9", "abc123"]);
+        assert_eq!(
+            result.group_names,
+            vec![
+                MappedGroup {
+                    name: "developers".to_string(),
+                    domain: IdOrName::from_name("federated")
+                },
+                MappedGroup {
+                    name: "testers".to_string(),
+                    domain: IdOrName::from_name("federated")
+                },
+            ]
+        );
+        assert!(result.projects.is_empty());
+    }
+
+    #[test]
+    fn test_apply_contractor() {
+        let result = mapping()
+            .apply(vec![
+                ("UserName", "bob"),
+                ("Email", "bob@example.com"),
+                ("orgPersonType", "Contractor"),
+            ])
+            .unwrap();
+        assert_eq!(result.matched_rules, vec![3]);
+        let user = result.user.unwrap();
+        assert_eq!(user.id.as_ref().unwrap(), "bob");
+        assert_eq!(user.name.as_ref().unwrap(), "bob");
+        assert_eq!(user.user_type.as_ref().unwrap(), "local");
+        assert_eq!(user.domain, Some(IdOrName::from_id("default")));
+        assert_eq!(
+            result.group_names,
+            vec![MappedGroup {
+                name: "Contractors".to_string(),
+                domain: IdOrName::from_id("default")
+            }]
+        );
+        assert_eq!(
+            result.projects,
+            vec![LocalProject {
+                name: "Contractor project for bob".to_string(),
+                roles: vec![LocalRole {
+                    name: "member".to_string()
+                }]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_apply_no_match() {
+        let result = mapping()
+            .apply(vec![("orgPersonType", "Guest"), ("Groups", "admins")])
+            .unwrap();
+        // The blacklist removed all values, the empty direct mapping still matches.
+        assert_eq!(result.matched_rules, vec![1]);
+        assert!(result.user.is_none());
+        assert!(result.group_names.is_empty());
+
+        let result = mapping().apply(Vec::<(&str, &str)>::new()).unwrap();
+        assert!(result.matched_rules.is_empty());
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let rules: Vec<MappingRule> = serde_json::from_str(
+            r#"[{"local": [{"user": {"name": "{1}"}}], "remote": [{"type": "UserName"}]}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            apply_mapping(&rules, vec![("UserName", "jdoe")]).unwrap_err(),
+            MappingError::MissingDirectMapping { rule: 0, index: 1 }
+        );
+
+        let rules: Vec<MappingRule> = serde_json::from_str(
+            r#"[{"local": [{"groups": "{0}"}], "remote": [{"type": "Groups"}]}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            apply_mapping(&rules, vec![("Groups", "a;b")]),
+            Err(MappingError::MissingGroupDomain { rule: 0, .. })
+        ));
+
+        let rules: Vec<MappingRule> = serde_json::from_str(
+            r#"[{"local": [], "remote": [{"type": "A", "any_one_of": ["("], "regex": true}]}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            apply_mapping(&rules, vec![("A", "a")]),
+            Err(MappingError::InvalidRegex { .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_direct_maps() {
+        let maps = vec![
+            vec!["a".to_string()],
+            vec!["b".to_string(), "c".to_string()],
+        ];
+        assert_eq!(
+            format_direct_maps("{0}-{{x}}-{1}", &maps, 0).unwrap(),
+            "a-{x}-['b', 'c']"
+        );
+        assert_eq!(parse_list("['b', 'c']"), vec!["b", "c"]);
+        assert_eq!(parse_list("b"), vec!["b"]);
+        assert!(parse_list("[]").is_empty());
+    }
+
+    #[test]
+    fn test_identity_provider() {
+        let idp = serde_json::from_str::<IdentityProviderRoot>(IDENTITY_PROVIDER)
+            .unwrap()
+            .identity_provider;
+        assert_eq!(idp.id, "ACME");
+        assert!(idp.enabled);
+        assert_eq!(idp.remote_ids, vec!["acme_id_1", "acme_id_2"]);
+        assert!(idp.authorization_ttl.is_none());
+
+        let value = IdentityProviderCreateRoot {
+            identity_provider: IdentityProviderCreate {
+                domain_id: Some("1789d1".to_string()),
+                enabled: Some(true),
+                remote_ids: Some(vec!["acme_id_1".to_string()]),
+                ..IdentityProviderCreate::default()
+            },
+        };
+        test::compare(
+            r#"{"identity_provider": {"domain_id": "1789d1", "enabled": true,
+                "remote_ids": ["acme_id_1"]}}"#,
+            value,
+        );
+    }
+
+    #[test]
+    fn test_protocols() {
+        let root: ProtocolsRoot = serde_json::from_str(PROTOCOLS).unwrap();
+        assert_eq!(root.protocols.len(), 2);
+        assert_eq!(root.protocols[0].mapping_id, "xyz234");
+        assert!(root.protocols[0].remote_id_attribute.is_none());
+        assert_eq!(
+            root.protocols[1].remote_id_attribute.as_ref().unwrap(),
+            "HTTP_OIDC_ISS"
+        );
+        test::compare(
+            r#"{"protocol": {"mapping_id": "xyz234"}}"#,
+            ProtocolCreateRoot {
+                protocol: ProtocolCreate {
+                    mapping_id: "xyz234".to_string(),
+                    remote_id_attribute: None,
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn test_service_providers() {
+        let root: ServiceProvidersRoot = serde_json::from_str(SERVICE_PROVIDERS).unwrap();
+        let sp = &root.service_providers[0];
+        assert_eq!(sp.id, "ACME");
+        assert_eq!(sp.relay_state_prefix.as_ref().unwrap(), "ss:mem:");
+        assert_eq!(sp.sp_url.path(), "/Shibboleth.sso/SAML2/ECP");
+    }
+
+    #[test]
+    fn test_service_provider_assertion_request() {
+        let root: ServiceProvidersRoot = serde_json::from_str(SERVICE_PROVIDERS).unwrap();
+        let request = root.service_providers[0].assertion_request("abcdef");
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "auth": {
+                    "identity": {
+                        "methods": ["token"],
+                        "token": {"id": "abcdef"}
+                    },
+                    "scope": {
+                        "service_provider": {"id": "ACME"}
+                    }
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,699 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Unified limits JSON structures and enforcement.
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::super::common::ResourceLinks;
+use super::projects::ProjectTree;
+
+/// A request to create a registered (default) limit.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegisteredLimitCreate {
+    pub service_id: String,
+    pub resource_name: String,
+    pub default_limit: i64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A request to create registered limits.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegisteredLimitsCreateRoot {
+    pub registered_limits: Vec<RegisteredLimitCreate>,
+}
+
+/// A request to update a registered limit.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RegisteredLimitUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub resource_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default_limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A registered limit update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegisteredLimitUpdateRoot {
+    pub registered_limit: RegisteredLimitUpdate,
+}
+
+/// A registered limit: the default for all projects.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegisteredLimit {
+    pub id: String,
+    pub service_id: String,
+    #[serde(default)]
+    pub region_id: Option<String>,
+    pub resource_name: String,
+    pub default_limit: i64,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A registered limit response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegisteredLimitRoot {
+    pub registered_limit: RegisteredLimit,
+}
+
+/// A list of registered limits.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegisteredLimitsRoot {
+    pub registered_limits: Vec<RegisteredLimit>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing registered limits.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RegisteredLimitFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_name: Option<String>,
+}
+
+/// A request to create a project or domain limit.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LimitCreate {
+    pub service_id: String,
+    pub resource_name: String,
+    pub resource_limit: i64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A request to create limits.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LimitsCreateRoot {
+    pub limits: Vec<LimitCreate>,
+}
+
+/// A request to update a limit.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct LimitUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub resource_limit: Option<i64>,
+}
+
+/// A limit update request root.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct LimitUpdateRoot {
+    pub limit: LimitUpdate,
+}
+
+/// A limit overriding the registered limit for a project or a domain.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Limit {
+    pub id: String,
+    pub service_id: String,
+    #[serde(default)]
+    pub region_id: Option<String>,
+    pub resource_name: String,
+    pub resource_limit: i64,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub domain_id: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A limit response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LimitRoot {
+    pub limit: Limit,
+}
+
+/// A list of limits.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LimitsRoot {
+    pub limits: Vec<Limit>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing limits.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LimitFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain_id: Option<String>,
+}
+
+/// Enforcement model name.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum LimitModelName {
+    /// Every project is limited independently.
+    #[serde(rename = "flat")]
+    Flat,
+    /// The usage of a top-level project and its children is limited by its limit.
+    #[serde(rename = "strict-two-level")]
+    StrictTwoLevel,
+}
+
+/// Enforcement model of the deployment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LimitModel {
+    pub name: LimitModelName,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// An enforcement model response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LimitModelRoot {
+    pub model: LimitModel,
+}
+
+/// A resource that would go over its limit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OverLimit {
+    /// Project whose limit would be exceeded.
+    pub project_id: String,
+    pub resource_name: String,
+    pub limit: i64,
+    /// Current usage (of the whole tree for a top-level project in `strict-two-level`).
+    pub current_usage: i64,
+    /// Requested change in usage.
+    pub delta: i64,
+}
+
+/// Error returned when enforcing limits.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LimitError {
+    /// One or more resources would go over their limits.
+    OverLimit(Vec<OverLimit>),
+    /// The project hierarchy is deeper than the model allows.
+    InvalidHierarchy(String),
+}
+
+/// Limits enforcement for one service, similar to oslo.limit.
+///
+/// The limit of a project is its project limit if any, otherwise the registered limit, otherwise
+/// zero.
+#[derive(Clone, Copy, Debug)]
+pub struct Enforcer<'a> {
+    model: LimitModelName,
+    registered_limits: &'a [RegisteredLimit],
+    limits: &'a [Limit],
+    service_id: Option<&'a str>,
+    region_id: Option<&'a str>,
+    hierarchy: &'a [ProjectTree],
+}
+
+impl LimitsRoot {
+    /// Find the limit of a project for a resource.
+    pub fn find(&self, project_id: &str, resource_name: &str) -> Option<&Limit> {
+        self.limits.iter().find(|limit| {
+            limit.project_id.as_deref() == Some(project_id) && limit.resource_name == resource_name
+        })
+    }
+}
+
+impl RegisteredLimitsRoot {
+    /// Find the registered limit for a resource.
+    pub fn find(&self, resource_name: &str) -> Option<&RegisteredLimit> {
+        self.registered_limits
+            .iter()
+            .find(|limit| limit.resource_name == resource_name)
+    }
+}
+
+impl<'a> Enforcer<'a> {
+    /// Create an enforcer for the given model and limits.
+    pub fn new(
+        model: LimitModelName,
+        registered_limits: &'a [RegisteredLimit],
+        limits: &'a [Limit],
+    ) -> Enforcer<'a> {
+        Enforcer {
+            model,
+            registered_limits,
+            limits,
+            service_id: None,
+            region_id: None,
+            hierarchy: &[],
+        }
+    }
+
+    /// Only consider limits of this service (and region, if provided).
+    pub fn with_service(self, service_id: &'a str, region_id: Option<&'a str>) -> Enforcer<'a> {
+        Enforcer {
+            service_id: Some(service_id),
+            region_id,
+            ..self
+        }
+    }
+
+    /// Use this project hierarchy (required for `strict-two-level`).
+    ///
+    /// Projects that are not in the hierarchy are treated as top-level without children.
+    pub fn with_hierarchy(self, hierarchy: &'a [ProjectTree]) -> Enforcer<'a> {
+        Enforcer { hierarchy, ..self }
+    }
+
+    /// Effective limit of a project for a resource.
+    pub fn effective_limit(&self, project_id: &str, resource_name: &str) -> i64 {
+        self.limits
+            .iter()
+            .find(|limit| {
+                limit.project_id.as_deref() == Some(project_id)
+                    && self.matches(&limit.service_id, &limit.region_id)
+                    && limit.resource_name == resource_name
+            })
+            .map(|limit| limit.resource_limit)
+            .or_else(|| {
+                self.registered_limits
+                    .iter()
+                    .find(|limit| {
+                        self.matches(&limit.service_id, &limit.region_id)
+                            && limit.resource_name == resource_name
+                    })
+                    .map(|limit| limit.default_limit)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Check whether claiming resources for a project is allowed.
+    ///
+    /// The `usage` callback receives a project ID and a resource name and returns the current
+    /// usage. All resources over their limits are reported in the error.
+    pub fn enforce<I, S, U>(&self, project_id: &str, deltas: I, usage: U) -> Result<(), LimitError>
+    where
+        I: IntoIterator<Item = (S, i64)>,
+        S: AsRef<str>,
+        U: Fn(&str, &str) -> i64,
+    {
+        let top = match self.model {
+            LimitModelName::Flat => None,
+            LimitModelName::StrictTwoLevel => self.top_level(project_id)?,
+        };
+
+        let mut over = Vec::new();
+        for (resource_name, delta) in deltas {
+            let resource_name = resource_name.as_ref();
+            let current = usage(project_id, resource_name);
+            let limit = self.effective_limit(project_id, resource_name);
+            if current + delta > limit {
+                over.push(OverLimit {
+                    project_id: project_id.to_string(),
+                    resource_name: resource_name.to_string(),
+                    limit,
+                    current_usage: current,
+                    delta,
+                });
+                continue;
+            }
+
+            match top {
+                Some(tree) if tree.project.id != project_id || !tree.children.is_empty() => {
+                    let tree_usage = tree
+                        .iter()
+                        .map(|project| usage(&project.id, resource_name))
+                        .sum::<i64>();
+                    let tree_limit = self.effective_limit(&tree.project.id, resource_name);
+                    if tree_usage + delta > tree_limit {
+                        over.push(OverLimit {
+                            project_id: tree.project.id.clone(),
+                            resource_name: resource_name.to_string(),
+                            limit: tree_limit,
+                            current_usage: tree_usage,
+                            delta,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if over.is_empty() {
+            Ok(())
+        } else {
+            Err(LimitError::OverLimit(over))
+        }
+    }
+
+    /// Whether claiming `delta` of a resource for a project is allowed.
+    #[inline]
+    pub fn is_allowed<U>(&self, project_id: &str, resource_name: &str, delta: i64, usage: U) -> bool
+    where
+        U: Fn(&str, &str) -> i64,
+    {
+        self.enforce(project_id, Some((resource_name, delta)), usage)
+            .is_ok()
+    }
+
+    fn matches(&self, service_id: &str, region_id: &Option<String>) -> bool {
+        self.service_id
+            .is_none_or(|expected| expected == service_id)
+            && self
+                .region_id
+                .is_none_or(|expected| region_id.as_deref() == Some(expected))
+    }
+
+    fn top_level(&self, project_id: &str) -> Result<Option<&'a ProjectTree>, LimitError> {
+        let top = match self
+            .hierarchy
+            .iter()
+            .find(|tree| tree.find(project_id).is_some())
+        {
+            Some(top) => top,
+            None => return Ok(None),
+        };
+
+        if top.children.iter().any(|child| !child.children.is_empty()) {
+            Err(LimitError::InvalidHierarchy(top.project.id.clone()))
+        } else {
+            Ok(Some(top))
+        }
+    }
+}
+
+impl fmt::Display for OverLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "resource {} of project {}: limit {}, current usage {}, delta {}",
+            self.resource_name, self.project_id, self.limit, self.current_usage, self.delta
+        )
+    }
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitError::OverLimit(over) => {
+                write!(f, "Limits exceeded:")?;
+                for item in over {
+                    write!(f, " {};", item)?;
+                }
+                Ok(())
+            }
+            LimitError::InvalidHierarchy(project_id) => write!(
+                f,
+                "Project {} has a hierarchy deeper than two levels",
+                project_id
+            ),
+        }
+    }
+}
+
+impl Error for LimitError {}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::super::super::common::test;
+    use super::super::projects::ProjectsRoot;
+    use super::*;
+
+    const LIMITS_CREATE: &str = r#"
+{
+    "limits": [
+        {
+            "service_id": "9408080f1970482aa0e38bc2d4ea34b7",
+            "resource_name": "snapshot",
+            "resource_limit": 5,
+            "project_id": "3a705b9f56bb439381b43c4fe59dccce",
+            "region_id": "RegionOne",
+            "description": "Number of snapshots for project 3a705b9f56bb439381b43c4fe59dccce"
+        }
+    ]
+}"#;
+
+    const REGISTERED_LIMITS: &str = r#"
+{
+    "links": {
+        "self": "http://10.3.150.25/identity/v3/registered_limits",
+        "previous": null,
+        "next": null
+    },
+    "registered_limits": [
+        {
+            "resource_name": "snapshot",
+            "region_id": "RegionOne",
+            "links": {
+                "self": "http://10.3.150.25/identity/v3/registered_limits/195acb8a093e43e9afb23d6628361e7c"
+            },
+            "default_limit": 10,
+            "service_id": "9408080f1970482aa0e38bc2d4ea34b7",
+            "id": "195acb8a093e43e9afb23d6628361e7c",
+            "description": "Number of snapshots per project"
+        },
+        {
+            "resource_name": "volume",
+            "region_id": null,
+            "links": {
+                "self": "http://10.3.150.25/identity/v3/registered_limits/ea5b78d70e2b4fd1a3e4ce1bb8f9ac06"
+            },
+            "default_limit": 5,
+            "service_id": "9408080f1970482aa0e38bc2d4ea34b7",
+            "id": "ea5b78d70e2b4fd1a3e4ce1bb8f9ac06",
+            "description": "Number of volumes per project"
+        }
+    ]
+}"#;
+
+    const LIMITS: &str = r#"
+{
+    "links": {
+        "self": "http://10.3.150.25/identity/v3/limits",
+        "previous": null,
+        "next": null
+    },
+    "limits": [
+        {
+            "resource_name": "volume",
+            "region_id": null,
+            "links": {
+                "self": "http://10.3.150.25/identity/v3/limits/25a04c7a065c430590881c646cdcdd58"
+            },
+            "service_id": "9408080f1970482aa0e38bc2d4ea34b7",
+            "project_id": "parent",
+            "domain_id": null,
+            "id": "25a04c7a065c430590881c646cdcdd58",
+            "resource_limit": 11,
+            "description": null
+        },
+        {
+            "resource_name": "volume",
+            "region_id": null,
+            "links": {
+                "self": "http://10.3.150.25/identity/v3/limits/3229b3849f584faea483d6851f7aab05"
+            },
+            "service_id": "9408080f1970482aa0e38bc2d4ea34b7",
+            "project_id": "child",
+            "domain_id": null,
+            "id": "3229b3849f584faea483d6851f7aab05",
+            "resource_limit": 8,
+            "description": null
+        }
+    ]
+}"#;
+
+    const MODEL: &str = r#"
+{
+    "model": {
+        "description": "A simple model that enforces limits on the project and its children.",
+        "name": "strict-two-level"
+    }
+}"#;
+
+    const PROJECTS: &str = r#"
+{
+    "projects": [
+        {"id": "parent", "name": "parent", "domain_id": "default", "parent_id": "default"},
+        {"id": "child", "name": "child", "domain_id": "default", "parent_id": "parent"},
+        {"id": "sibling", "name": "sibling", "domain_id": "default", "parent_id": "parent"},
+        {"id": "alone", "name": "alone", "domain_id": "default", "parent_id": "default"}
+    ]
+}"#;
+
+    fn fixtures() -> (Vec<RegisteredLimit>, Vec<Limit>, Vec<ProjectTree>) {
+        let registered: RegisteredLimitsRoot = serde_json::from_str(REGISTERED_LIMITS).unwrap();
+        let limits: LimitsRoot = serde_json::from_str(LIMITS).unwrap();
+        let projects: ProjectsRoot = serde_json::from_str(PROJECTS).unwrap();
+        (
+            registered.registered_limits,
+            limits.limits,
+            ProjectTree::build(projects.projects),
+        )
+    }
+
+    fn usage(values: &[(&str, i64)]) -> impl Fn(&str, &str) -> i64 {
+        let values = values
+            .iter()
+            .map(|(project, value)| (project.to_string(), *value))
+            .collect::<HashMap<_, _>>();
+        move |project, resource| {
+            if resource == "volume" {
+                values.get(project).cloned().unwrap_or(0)
+            } else {
+                0
+            }
+        }
+    }
+
+    #[test]
+    fn test_limits_create() {
+        let value = LimitsCreateRoot {
+            limits: vec![LimitCreate {
+                service_id: "9408080f1970482aa0e38bc2d4ea34b7".to_string(),
+                resource_name: "snapshot".to_string(),
+                resource_limit: 5,
+                project_id: Some("3a705b9f56bb439381b43c4fe59dccce".to_string()),
+                domain_id: None,
+                region_id: Some("RegionOne".to_string()),
+                description: Some(
+                    "Number of snapshots for project 3a705b9f56bb439381b43c4fe59dccce".to_string(),
+                ),
+            }],
+        };
+        test::compare(LIMITS_CREATE, &value);
+        test::round_trip::<LimitsCreateRoot>(LIMITS_CREATE);
+    }
+
+    #[test]
+    fn test_limits() {
+        let (registered, limits, _) = fixtures();
+        assert_eq!(registered.len(), 2);
+        assert_eq!(limits.len(), 2);
+
+        let root: LimitsRoot = serde_json::from_str(LIMITS).unwrap();
+        assert_eq!(root.find("child", "volume").unwrap().resource_limit, 8);
+        assert!(root.find("child", "snapshot").is_none());
+        let root: RegisteredLimitsRoot = serde_json::from_str(REGISTERED_LIMITS).unwrap();
+        assert_eq!(root.find("snapshot").unwrap().default_limit, 10);
+
+        let root: LimitModelRoot = serde_json::from_str(MODEL).unwrap();
+        assert_eq!(root.model.name, LimitModelName::StrictTwoLevel);
+    }
+
+    #[test]
+    fn test_effective_limit() {
+        let (registered, limits, _) = fixtures();
+        let enforcer = Enforcer::new(LimitModelName::Flat, &registered, &limits);
+        assert_eq!(enforcer.effective_limit("child", "volume"), 8);
+        assert_eq!(enforcer.effective_limit("alone", "volume"), 5);
+        assert_eq!(enforcer.effective_limit("alone", "snapshot"), 10);
+        assert_eq!(enforcer.effective_limit("alone", "backup"), 0);
+
+        let enforcer = enforcer.with_service("9408080f1970482aa0e38bc2d4ea34b7", Some("RegionTwo"));
+        assert_eq!(enforcer.effective_limit("alone", "snapshot"), 0);
+        let enforcer = enforcer.with_service("other", None);
+        assert_eq!(enforcer.effective_limit("child", "volume"), 0);
+    }
+
+    #[test]
+    fn test_enforce_flat() {
+        let (registered, limits, _) = fixtures();
+        let enforcer = Enforcer::new(LimitModelName::Flat, &registered, &limits);
+        let usage = usage(&[("parent", 10), ("child", 6)]);
+        assert!(enforcer.is_allowed("child", "volume", 2, &usage));
+        assert!(enforcer.is_allowed("parent", "volume", 1, &usage));
+        assert!(enforcer.is_allowed("alone", "volume", 5, &usage));
+        assert!(!enforcer.is_allowed("alone", "volume", 6, &usage));
+
+        let err = enforcer
+            .enforce("child", vec![("volume", 3), ("snapshot", 1)], &usage)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            LimitError::OverLimit(vec![OverLimit {
+                project_id: "child".to_string(),
+                resource_name: "volume".to_string(),
+                limit: 8,
+                current_usage: 6,
+                delta: 3,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_enforce_strict_two_level() {
+        let (registered, limits, trees) = fixtures();
+        let enforcer = Enforcer::new(LimitModelName::StrictTwoLevel, &registered, &limits)
+            .with_hierarchy(&trees);
+        let usage = usage(&[("parent", 2), ("child", 6), ("sibling", 1)]);
+        assert!(enforcer.is_allowed("child", "volume", 2, &usage));
+        assert!(enforcer.is_allowed("sibling", "volume", 2, &usage));
+        assert!(enforcer.is_allowed("alone", "volume", 5, &usage));
+        assert!(!enforcer.is_allowed("alone", "volume", 6, &usage));
+        // The sibling is within its registered limit, but the tree is not.
+        let err = enforcer
+            .enforce("sibling", Some(("volume", 3)), &usage)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            LimitError::OverLimit(vec![OverLimit {
+                project_id: "parent".to_string(),
+                resource_name: "volume".to_string(),
+                limit: 11,
+                current_usage: 9,
+                delta: 3,
+            }])
+        );
+        assert!(!enforcer.is_allowed("parent", "volume", 3, &usage));
+    }
+
+    #[test]
+    fn test_enforce_invalid_hierarchy() {
+        let (registered, limits, _) = fixtures();
+        let projects: ProjectsRoot = serde_json::from_str(PROJECTS).unwrap();
+        let mut projects = projects.projects;
+        projects[1].parent_id = Some("sibling".to_string());
+        let trees = ProjectTree::build(projects);
+        let enforcer = Enforcer::new(LimitModelName::StrictTwoLevel, &registered, &limits)
+            .with_hierarchy(&trees);
+        assert_eq!(
+            enforcer.enforce("child", Some(("volume", 1)), |_, _| 0),
+            Err(LimitError::InvalidHierarchy("parent".to_string()))
+        );
+        assert!(enforcer.is_allowed("alone", "volume", 1, |_, _| 0));
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! OpenID Connect token exchange structures.
+//!
+//! The `v3oidcpassword` and `v3oidcclientcredentials` flows post an `OidcTokenRequest` to the
+//! token endpoint of the identity provider (authenticating the client with HTTP basic auth using
+//! the client ID and secret). The resulting access token is then exchanged for a Keystone token
+//! by posting to `Federated::auth_path` with `OidcTokenResponse::authorization` as the
+//! `Authorization` header. The `v3oidcaccesstoken` flow starts from an existing access token.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
+use url::Url;
+
+use super::REDACTED;
+
+/// Default OpenID Connect scope.
+pub const DEFAULT_SCOPE: &str = "openid";
+
+/// An OAuth2 token request.
+#[derive(Clone, PartialEq, Eq)]
+pub enum OidcTokenRequest {
+    /// Resource owner password credentials grant.
+    Password {
+        username: String,
+        password: String,
+        scope: String,
+    },
+    /// Client credentials grant.
+    ClientCredentials { scope: String },
+}
+
+/// An OAuth2 token response.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct OidcTokenResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub token_type: Option<String>,
+    /// Lifetime of the access token in seconds.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expires_in: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub scope: Option<String>,
+}
+
+/// OpenID Connect discovery document (`.well-known/openid-configuration`).
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcDiscovery {
+    pub issuer: String,
+    pub token_endpoint: Url,
+    #[serde(default)]
+    pub authorization_endpoint: Option<Url>,
+    #[serde(default)]
+    pub grant_types_supported: Vec<String>,
+}
+
+impl OidcTokenRequest {
+    /// A password grant request with the default scope.
+    pub fn password<S1, S2>(username: S1, password: S2) -> OidcTokenRequest
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        OidcTokenRequest::Password {
+            username: username.into(),
+            password: password.into(),
+            scope: DEFAULT_SCOPE.to_string(),
+        }
+    }
+
+    /// A client credentials grant request with the default scope.
+    pub fn client_credentials() -> OidcTokenRequest {
+        OidcTokenRequest::ClientCredentials {
+            scope: DEFAULT_SCOPE.to_string(),
+        }
+    }
+
+    /// Grant type of this request.
+    pub fn grant_type(&self) -> &'static str {
+        match *self {
+            OidcTokenRequest::Password { .. } => "password",
+            OidcTokenRequest::ClientCredentials { .. } => "client_credentials",
+        }
+    }
+
+    /// A copy with the password replaced by a placeholder.
+    pub fn redacted(&self) -> OidcTokenRequest {
+        match *self {
+            OidcTokenRequest::Password {
+                ref username,
+                ref scope,
+                ..
+            } => OidcTokenRequest::Password {
+                username: username.clone(),
+                password: REDACTED.to_string(),
+                scope: scope.clone(),
+            },
+            OidcTokenRequest::ClientCredentials { .. } => self.clone(),
+        }
+    }
+
+    /// Encode the request as an `application/x-www-form-urlencoded` body.
+    pub fn form_body(&self) -> String {
+        let mut ser = form_urlencoded::Serializer::new(String::new());
+        let _ = ser.append_pair("grant_type", self.grant_type());
+        match *self {
+            OidcTokenRequest::Password {
+                ref username,
+                ref password,
+                ref scope,
+            } => {
+                let _ = ser
+                    .append_pair("username", username)
+                    .append_pair("password", password)
+                    .append_pair("scope", scope);
+            }
+            OidcTokenRequest::ClientCredentials { ref scope } => {
+                let _ = ser.append_pair("scope", scope);
+            }
+        }
+        ser.finish()
+    }
+}
+
+impl fmt::Debug for OidcTokenRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OidcTokenRequest::Password {
+                ref username,
+                ref scope,
+                ..
+            } => f
+                .debug_struct("Password")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .field("scope", scope)
+                .finish(),
+            OidcTokenRequest::ClientCredentials { ref scope } => f
+                .debug_struct("ClientCredentials")
+                .field("scope", scope)
+                .finish(),
+        }
+    }
+}
+
+impl OidcTokenResponse {
+    /// Value of the `Authorization` header for exchanging the access token.
+    pub fn authorization(&self) -> String {
+        format!("Bearer {}", self.access_token)
+    }
+}
+
+impl fmt::Debug for OidcTokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OidcTokenResponse")
+            .field("access_token", &REDACTED)
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| REDACTED),
+            )
+            .field("id_token", &self.id_token.as_ref().map(|_| REDACTED))
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::*;
+
+    const TOKEN_RESPONSE: &str = r#"
+{
+    "access_token": "abcd",
+    "token_type": "Bearer",
+    "expires_in": 300,
+    "refresh_token": "efgh",
+    "scope": "openid profile"
+}"#;
+
+    const DISCOVERY: &str = r#"
+{
+    "issuer": "https://idp.example.com",
+    "token_endpoint": "https://idp.example.com/token",
+    "grant_types_supported": ["password", "client_credentials"]
+}"#;
+
+    #[test]
+    fn test_token_request_form_body() {
+        let req = OidcTokenRequest::password("user", "p@ss word");
+        assert_eq!(
+            req.form_body(),
+            "grant_type=password&username=user&password=p%40ss+word&scope=openid"
+        );
+        let req = OidcTokenRequest::client_credentials();
+        assert_eq!(
+            req.form_body(),
+            "grant_type=client_credentials&scope=openid"
+        );
+    }
+
+    #[test]
+    fn test_token_request_redacted() {
+        let req = OidcTokenRequest::password("user", "secret");
+        assert!(!format!("{:?}", req).contains("secret"));
+        assert!(!req.redacted().form_body().contains("secret"));
+    }
+
+    #[test]
+    fn test_token_response() {
+        test::round_trip::<OidcTokenResponse>(TOKEN_RESPONSE);
+        let value: OidcTokenResponse = serde_json::from_str(TOKEN_RESPONSE).unwrap();
+        assert_eq!(value.authorization(), "Bearer abcd");
+        assert_eq!(value.expires_in, Some(300));
+        let debug = format!("{:?}", value);
+        assert!(!debug.contains("abcd"));
+        assert!(!debug.contains("efgh"));
+    }
+
+    #[test]
+    fn test_discovery() {
+        let value: OidcDiscovery = serde_json::from_str(DISCOVERY).unwrap();
+        assert_eq!(
+            value.token_endpoint.as_str(),
+            "https://idp.example.com/token"
+        );
+        assert!(value.authorization_endpoint.is_none());
+        assert_eq!(value.grant_types_supported.len(), 2);
+    }
+}
@@ -0,0 +1,812 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Projects and domains JSON structures and protocol bits.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::super::common::ResourceLinks;
+use super::IdOrName;
+
+/// Project or domain options.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProjectOptions {
+    /// Whether the resource is protected from changes and deletion.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub immutable: Option<bool>,
+}
+
+/// A request to create a project.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ProjectCreate {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain_id: Option<String>,
+    /// Parent project or domain (defaults to the domain).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    /// Whether the project acts as a domain.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub is_domain: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub options: Option<ProjectOptions>,
+}
+
+/// A project creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProjectCreateRoot {
+    pub project: ProjectCreate,
+}
+
+/// A request to update a project.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ProjectUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    /// New tags replacing the existing ones.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub options: Option<ProjectOptions>,
+}
+
+/// A project update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProjectUpdateRoot {
+    pub project: ProjectUpdate,
+}
+
+/// Tags of a project, used both in requests and responses of the tags API.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ProjectTags {
+    pub tags: Vec<String>,
+}
+
+/// A project.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    /// Owning domain (`None` for projects acting as domains).
+    #[serde(default)]
+    pub domain_id: Option<String>,
+    /// Parent project or domain (`None` for domains).
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "super::default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub is_domain: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub options: ProjectOptions,
+    #[serde(default)]
+    pub links: ResourceLinks,
+    /// Parents, when requested with `parents_as_list` or `parents_as_ids`.
+    #[serde(default)]
+    pub parents: Option<ProjectHierarchy>,
+    /// Children, when requested with `subtree_as_list` or `subtree_as_ids`.
+    #[serde(default)]
+    pub subtree: Option<ProjectHierarchy>,
+}
+
+/// A project response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProjectRoot {
+    pub project: Project,
+}
+
+/// A list of projects.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProjectsRoot {
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Parents or subtree of a project.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ProjectHierarchy {
+    /// Full projects (the `*_as_list` variants).
+    List(Vec<ProjectRoot>),
+    /// Nested project IDs (the `*_as_ids` variants).
+    Ids(ProjectIds),
+}
+
+/// Nested project IDs, each mapped to the next level of the hierarchy.
+///
+/// For parents the next level contains the parent of a project, for subtree - its children.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ProjectIds(pub BTreeMap<String, Option<ProjectIds>>);
+
+/// Query parameters for showing a project.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ProjectShowQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parents_as_list: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parents_as_ids: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtree_as_list: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtree_as_ids: Option<bool>,
+}
+
+/// Query filters for listing projects.
+///
+/// Tag filters are comma-separated lists of tags.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ProjectFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_domain: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "tags-any")]
+    pub tags_any: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "not-tags")]
+    pub not_tags: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "not-tags-any")]
+    pub not_tags_any: Option<String>,
+}
+
+/// A project with its children.
+#[derive(Clone, Debug)]
+pub struct ProjectTree {
+    pub project: Project,
+    pub children: Vec<ProjectTree>,
+}
+
+/// A project ID with the IDs of its children.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProjectIdTree {
+    pub id: String,
+    pub children: Vec<ProjectIdTree>,
+}
+
+/// A request to create a domain.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DomainCreate {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub options: Option<ProjectOptions>,
+}
+
+/// A domain creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DomainCreateRoot {
+    pub domain: DomainCreate,
+}
+
+/// A request to update a domain.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DomainUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub options: Option<ProjectOptions>,
+}
+
+/// A domain update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DomainUpdateRoot {
+    pub domain: DomainUpdate,
+}
+
+/// A domain.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Domain {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "super::default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub options: ProjectOptions,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A domain response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DomainRoot {
+    pub domain: Domain,
+}
+
+/// A list of domains.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DomainsRoot {
+    pub domains: Vec<Domain>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Query filters for listing domains.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DomainFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Project {
+    /// Whether the project matches the reference.
+    pub fn matches(&self, reference: &IdOrName) -> bool {
+        match reference {
+            IdOrName::Id(ref id) => self.id == *id,
+            IdOrName::Name(ref name) => self.name == *name,
+        }
+    }
+
+    /// Rebuild the tree containing this project from its parents and subtree.
+    ///
+    /// Only the `*_as_list` forms provide enough information, the `*_as_ids` forms are
+    /// ignored (use `id_tree` for them). The root of the result is the top-most known parent.
+    /// Returns `None` if the parent links form a cycle.
+    pub fn tree(&self) -> Option<ProjectTree> {
+        let mut projects = Vec::new();
+        for hierarchy in self.parents.iter().chain(self.subtree.iter()) {
+            if let ProjectHierarchy::List(ref items) = hierarchy {
+                projects.extend(items.iter().map(|item| item.project.without_hierarchy()));
+            }
+        }
+        projects.push(self.without_hierarchy());
+        ProjectTree::build(projects)
+            .into_iter()
+            .find(|tree| tree.find(&self.id).is_some())
+    }
+
+    /// Rebuild the tree of IDs containing this project from its parents and subtree.
+    ///
+    /// Works with both the `*_as_list` and the `*_as_ids` forms. The root of the result is the
+    /// top-most known parent.
+    pub fn id_tree(&self) -> ProjectIdTree {
+        let children = match self.subtree {
+            Some(ProjectHierarchy::Ids(ref ids)) => ids.trees(),
+            Some(ProjectHierarchy::List(ref items)) => {
+                let mut projects = items
+                    .iter()
+                    .map(|item| item.project.without_hierarchy())
+                    .collect::<Vec<_>>();
+                projects.push(self.without_hierarchy());
+                ProjectTree::build(projects)
+                    .iter()
+                    .find_map(|tree| tree.find(&self.id))
+                    .map(|tree| tree.children.iter().map(ProjectIdTree::from).collect())
+                    .unwrap_or_default()
+            }
+            None => Vec::new(),
+        };
+
+        let mut result = ProjectIdTree {
+            id: self.id.clone(),
+            children,
+        };
+        if let Some(ref parents) = self.parents {
+            for parent_id in parents.ids() {
+                result = ProjectIdTree {
+                    id: parent_id.to_string(),
+                    children: vec![result],
+                };
+            }
+        }
+        result
+    }
+
+    fn without_hierarchy(&self) -> Project {
+        Project {
+            parents: None,
+            subtree: None,
+            ..self.clone()
+        }
+    }
+}
+
+impl ProjectsRoot {
+    /// Find a project by its ID or name.
+    ///
+    /// Project names are only unique within a domain.
+    pub fn find(&self, reference: &IdOrName) -> Option<&Project> {
+        self.projects
+            .iter()
+            .find(|project| project.matches(reference))
+    }
+}
+
+impl ProjectHierarchy {
+    /// IDs of all projects in the hierarchy.
+    ///
+    /// For parents the nearest parent comes first.
+    pub fn ids(&self) -> Vec<&str> {
+        match self {
+            ProjectHierarchy::List(ref items) => {
+                items.iter().map(|item| item.project.id.as_str()).collect()
+            }
+            ProjectHierarchy::Ids(ref ids) => ids.ids(),
+        }
+    }
+}
+
+impl ProjectIds {
+    /// All IDs in depth-first order.
+    pub fn ids(&self) -> Vec<&str> {
+        let mut result = Vec::new();
+        self.collect(&mut result);
+        result
+    }
+
+    /// Convert to trees of IDs.
+    pub fn trees(&self) -> Vec<ProjectIdTree> {
+        self.0
+            .iter()
+            .map(|(id, next)| ProjectIdTree {
+                id: id.clone(),
+                children: next.as_ref().map(ProjectIds::trees).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    fn collect<'a>(&'a self, result: &mut Vec<&'a str>) {
+        for (id, next) in &self.0 {
+            result.push(id.as_str());
+            if let Some(next) = next {
+                next.collect(result);
+            }
+        }
+    }
+}
+
+impl ProjectIdTree {
+    /// Find a project in the tree by its ID.
+    pub fn find(&self, id: &str) -> Option<&ProjectIdTree> {
+        if self.id == id {
+            Some(self)
+        } else {
+            self.children.iter().find_map(|child| child.find(id))
+        }
+    }
+
+    /// All IDs in depth-first order.
+    pub fn ids(&self) -> Vec<&str> {
+        let mut result = vec![self.id.as_str()];
+        for child in &self.children {
+            result.extend(child.ids());
+        }
+        result
+    }
+}
+
+impl From<&ProjectTree> for ProjectIdTree {
+    fn from(value: &ProjectTree) -> ProjectIdTree {
+        ProjectIdTree {
+            id: value.project.id.clone(),
+            children: value.children.iter().map(ProjectIdTree::from).collect(),
+        }
+    }
+}
+
+impl ProjectTree {
+    /// Build trees from a flat list of projects.
+    ///
+    /// Projects whose parent is not in the list become roots. The original order is kept for
+    /// roots and for children of the same parent, duplicates are ignored.
+    pub fn build(projects: Vec<Project>) -> Vec<ProjectTree> {
+        let mut seen = HashSet::new();
+        let projects = projects
+            .into_iter()
+            .filter(|project| seen.insert(project.id.clone()))
+            .collect::<Vec<_>>();
+
+        let mut roots = Vec::new();
+        let mut children: HashMap<String, Vec<Project>> = HashMap::new();
+        for project in projects {
+            match project.parent_id {
+                Some(ref parent_id) if seen.contains(parent_id) => {
+                    children.entry(parent_id.clone()).or_default().push(project);
+                }
+                _ => roots.push(project),
+            }
+        }
+
+        roots
+            .into_iter()
+            .map(|project| ProjectTree::attach(project, &mut children))
+            .collect()
+    }
+
+    /// Find a project in the tree by its ID.
+    pub fn find(&self, id: &str) -> Option<&ProjectTree> {
+        if self.project.id == id {
+            Some(self)
+        } else {
+            self.children.iter().find_map(|child| child.find(id))
+        }
+    }
+
+    /// Iterate over all projects in the tree in depth-first order.
+    pub fn iter(&self) -> impl Iterator<Item = &Project> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let current = stack.pop()?;
+            stack.extend(current.children.iter().rev());
+            Some(&current.project)
+        })
+    }
+
+    fn attach(project: Project, children: &mut HashMap<String, Vec<Project>>) -> ProjectTree {
+        let direct = children.remove(&project.id).unwrap_or_default();
+        ProjectTree {
+            children: direct
+                .into_iter()
+                .map(|child| ProjectTree::attach(child, children))
+                .collect(),
+            project,
+        }
+    }
+}
+
+impl Domain {
+    /// Whether the domain matches the reference.
+    pub fn matches(&self, reference: &IdOrName) -> bool {
+        match reference {
+            IdOrName::Id(ref id) => self.id == *id,
+            IdOrName::Name(ref name) => self.name == *name,
+        }
+    }
+}
+
+impl DomainsRoot {
+    /// Find a domain by its ID or name.
+    pub fn find(&self, reference: &IdOrName) -> Option<&Domain> {
+        self.domains.iter().find(|domain| domain.matches(reference))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::super::IdOrName;
+    use super::*;
+
+    const PROJECT_CREATE: &str = r#"
+{
+    "project": {
+        "name": "myNewProject",
+        "domain_id": "1789d1",
+        "parent_id": "123c56",
+        "description": "My new project",
+        "enabled": true,
+        "is_domain": false,
+        "tags": ["tag1", "tag2"],
+        "options": {
+            "immutable": true
+        }
+    }
+}"#;
+
+    const PROJECT_PARENTS_AS_LIST: &str = r#"
+{
+    "project": {
+        "domain_id": "default",
+        "enabled": true,
+        "id": "263fd9",
+        "is_domain": false,
+        "links": {
+            "self": "http://example.com/identity/v3/projects/263fd9"
+        },
+        "name": "Dev Team",
+        "parent_id": "183a0e",
+        "parents": [
+            {
+                "project": {
+                    "domain_id": "default",
+                    "enabled": true,
+                    "id": "183a0e",
+                    "is_domain": false,
+                    "name": "Engineering",
+                    "parent_id": "default",
+                    "tags": []
+                }
+            },
+            {
+                "project": {
+                    "domain_id": null,
+                    "enabled": true,
+                    "id": "default",
+                    "is_domain": true,
+                    "name": "Default",
+                    "parent_id": null,
+                    "tags": []
+                }
+            }
+        ],
+        "subtree": [
+            {
+                "project": {
+                    "domain_id": "default",
+                    "enabled": true,
+                    "id": "9ab6c3",
+                    "is_domain": false,
+                    "name": "Backend",
+                    "parent_id": "263fd9"
+                }
+            },
+            {
+                "project": {
+                    "domain_id": "default",
+                    "enabled": false,
+                    "id": "4e21fa",
+                    "is_domain": false,
+                    "name": "Frontend",
+                    "parent_id": "263fd9"
+                }
+            },
+            {
+                "project": {
+                    "domain_id": "default",
+                    "enabled": true,
+                    "id": "77aa01",
+                    "is_domain": false,
+                    "name": "Database",
+                    "parent_id": "9ab6c3"
+                }
+            }
+        ],
+        "tags": ["dev"],
+        "options": {}
+    }
+}"#;
+
+    const PROJECT_AS_IDS: &str = r#"
+{
+    "project": {
+        "domain_id": "default",
+        "enabled": true,
+        "id": "263fd9",
+        "is_domain": false,
+        "name": "Dev Team",
+        "parent_id": "183a0e",
+        "parents": {
+            "183a0e": {
+                "default": null
+            }
+        },
+        "subtree": {
+            "9ab6c3": {
+                "77aa01": null
+            },
+            "4e21fa": null
+        }
+    }
+}"#;
+
+    const DOMAINS: &str = r#"
+{
+    "domains": [
+        {
+            "description": "Used for swift functional testing",
+            "enabled": true,
+            "id": "5a75994a",
+            "links": {
+                "self": "http://example.com/identity/v3/domains/5a75994a"
+            },
+            "name": "swift_test",
+            "options": {
+                "immutable": true
+            },
+            "tags": []
+        },
+        {
+            "description": "Owns users and tenants (i.e. projects) available on Identity API v2.",
+            "enabled": true,
+            "id": "default",
+            "name": "Default"
+        }
+    ],
+    "links": {
+        "next": null,
+        "previous": null,
+        "self": "http://example.com/identity/v3/domains"
+    }
+}"#;
+
+    #[test]
+    fn test_project_create() {
+        let value = ProjectCreateRoot {
+            project: ProjectCreate {
+                name: "myNewProject".to_string(),
+                domain_id: Some("1789d1".to_string()),
+                parent_id: Some("123c56".to_string()),
+                description: Some("My new project".to_string()),
+                enabled: Some(true),
+                is_domain: Some(false),
+                tags: vec!["tag1".to_string(), "tag2".to_string()],
+                options: Some(ProjectOptions {
+                    immutable: Some(true),
+                }),
+            },
+        };
+        test::compare(PROJECT_CREATE, value);
+        test::round_trip::<ProjectCreateRoot>(PROJECT_CREATE);
+    }
+
+    #[test]
+    fn test_project_update() {
+        let value = ProjectUpdateRoot {
+            project: ProjectUpdate {
+                enabled: Some(false),
+                tags: Some(Vec::new()),
+                ..ProjectUpdate::default()
+            },
+        };
+        test::compare(r#"{"project": {"enabled": false, "tags": []}}"#, value);
+    }
+
+    #[test]
+    fn test_project_as_list() {
+        let project = serde_json::from_str::<ProjectRoot>(PROJECT_PARENTS_AS_LIST)
+            .unwrap()
+            .project;
+        assert_eq!(project.tags, vec!["dev".to_string()]);
+        assert!(project.options.immutable.is_none());
+        let parents = project.parents.as_ref().unwrap();
+        assert_eq!(parents.ids(), vec!["183a0e", "default"]);
+        let subtree = project.subtree.as_ref().unwrap();
+        assert_eq!(subtree.ids(), vec!["9ab6c3", "4e21fa", "77aa01"]);
+
+        let tree = project.tree().unwrap();
+        assert_eq!(tree.project.id, "default");
+        assert!(tree.project.is_domain);
+        assert_eq!(
+            tree.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+            vec!["default", "183a0e", "263fd9", "9ab6c3", "77aa01", "4e21fa"]
+        );
+        let node = tree.find("263fd9").unwrap();
+        assert!(node.project.parents.is_none());
+        assert_eq!(node.children.len(), 2);
+        assert_eq!(node.children[0].children[0].project.name, "Database");
+        assert!(!node.children[1].project.enabled);
+
+        assert_eq!(project.id_tree(), ProjectIdTree::from(&tree));
+    }
+
+    #[test]
+    fn test_project_as_ids() {
+        let project = serde_json::from_str::<ProjectRoot>(PROJECT_AS_IDS)
+            .unwrap()
+            .project;
+        let parents = project.parents.as_ref().unwrap();
+        assert_eq!(parents.ids(), vec!["183a0e", "default"]);
+        let subtree = project.subtree.as_ref().unwrap();
+        assert_eq!(subtree.ids(), vec!["4e21fa", "9ab6c3", "77aa01"]);
+
+        let tree = project.tree().unwrap();
+        assert_eq!(tree.project.id, "263fd9");
+        assert!(tree.children.is_empty());
+
+        let tree = project.id_tree();
+        assert_eq!(
+            tree.ids(),
+            vec!["default", "183a0e", "263fd9", "4e21fa", "9ab6c3", "77aa01"]
+        );
+        let own = tree.find("263fd9").unwrap();
+        assert_eq!(own.children.len(), 2);
+        assert_eq!(own.find("9ab6c3").unwrap().children[0].id, "77aa01");
+        assert!(tree.find("unknown").is_none());
+    }
+
+    #[test]
+    fn test_project_tree_build() {
+        let projects: ProjectsRoot = serde_json::from_str(
+            r#"
+{
+    "projects": [
+        {"id": "c", "name": "C", "domain_id": "d", "parent_id": "b"},
+        {"id": "a", "name": "A", "domain_id": "d", "parent_id": "d"},
+        {"id": "b", "name": "B", "domain_id": "d", "parent_id": "a"},
+        {"id": "x", "name": "X", "domain_id": "d", "parent_id": "y"},
+        {"id": "y", "name": "Y", "domain_id": "d", "parent_id": "x"},
+        {"id": "a", "name": "A", "domain_id": "d", "parent_id": "d"}
+    ]
+}"#,
+        )
+        .unwrap();
+        assert_eq!(projects.find(&IdOrName::from_name("B")).unwrap().id, "b");
+
+        let trees = ProjectTree::build(projects.projects);
+        assert_eq!(trees.len(), 1);
+        assert_eq!(
+            trees[0].iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+        assert!(trees[0].find("x").is_none());
+    }
+
+    #[test]
+    fn test_project_filter() {
+        let filter = ProjectFilter {
+            parent_id: Some("183a0e".to_string()),
+            tags_any: Some("dev,qa".to_string()),
+            not_tags: Some("deprecated".to_string()),
+            ..ProjectFilter::default()
+        };
+        test::compare(
+            r#"{"parent_id": "183a0e", "tags-any": "dev,qa", "not-tags": "deprecated"}"#,
+            filter,
+        );
+    }
+
+    #[test]
+    fn test_domain_create() {
+        let value = DomainCreateRoot {
+            domain: DomainCreate {
+                name: "myDomain".to_string(),
+                enabled: Some(true),
+                ..DomainCreate::default()
+            },
+        };
+        test::compare(
+            r#"{"domain": {"name": "myDomain", "enabled": true}}"#,
+            value,
+        );
+    }
+
+    #[test]
+    fn test_domains() {
+        let root: DomainsRoot = serde_json::from_str(DOMAINS).unwrap();
+        assert_eq!(root.domains.len(), 2);
+        assert_eq!(root.domains[0].options.immutable, Some(true));
+        assert!(root.domains[1].links.self_link.is_none());
+        assert_eq!(
+            root.find(&IdOrName::from_name("Default")).unwrap().id,
+            "default"
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! OS-REVOKE JSON structures and revocation checking.
+
+use chrono::{DateTime, FixedOffset, Timelike};
+use serde::{Deserialize, Serialize};
+
+use super::super::common::{self, ResourceLinks};
+use super::{Token, TokenScope};
+
+/// A token revocation event.
+///
+/// Every attribute that is set must match the token for the event to apply.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RevocationEvent {
+    /// Only tokens issued before (or at) this time are revoked.
+    #[serde(deserialize_with = "common::datetime_as_utc")]
+    pub issued_before: DateTime<FixedOffset>,
+    #[serde(deserialize_with = "common::opt_datetime_as_utc", default)]
+    pub revoked_at: Option<DateTime<FixedOffset>>,
+    /// User of the token (also matches trustors and trustees of trusts).
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    /// Domain of the user or of the scope.
+    #[serde(default)]
+    pub domain_id: Option<String>,
+    /// Domain of the scope.
+    #[serde(default)]
+    pub domain_scope_id: Option<String>,
+    #[serde(default)]
+    pub role_id: Option<String>,
+    #[serde(default)]
+    pub trust_id: Option<String>,
+    #[serde(rename = "OS-OAUTH1:consumer_id", default)]
+    pub consumer_id: Option<String>,
+    #[serde(rename = "OS-OAUTH1:access_token_id", default)]
+    pub access_token_id: Option<String>,
+    /// Audit ID of a single token.
+    #[serde(default)]
+    pub audit_id: Option<String>,
+    /// Audit ID of the original token, revokes all tokens derived from it.
+    #[serde(default)]
+    pub audit_chain_id: Option<String>,
+    /// Expiration time of the token (compared with second precision).
+    #[serde(deserialize_with = "common::opt_datetime_as_utc", default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
+}
+
+/// A list of revocation events.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RevocationEventsRoot {
+    pub events: Vec<RevocationEvent>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Filters for listing revocation events.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct RevocationEventFilter {
+    /// Only return events since this time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<FixedOffset>>,
+}
+
+impl RevocationEvent {
+    /// Whether the event revokes the token.
+    ///
+    /// Implements the same matching rules as Keystone.
+    pub fn matches(&self, token: &Token) -> bool {
+        if token.issued_at > self.issued_before {
+            return false;
+        }
+
+        if let Some(ref user_id) = self.user_id {
+            let trust_user = token.trust.as_ref().is_some_and(|trust| {
+                &trust.trustor_user.id == user_id || &trust.trustee_user.id == user_id
+            });
+            if &token.user.id != user_id && !trust_user {
+                return false;
+            }
+        }
+
+        let scope_domain_id = match token.scope() {
+            TokenScope::Project(project) => Some(&project.domain.id),
+            TokenScope::Domain(domain) => Some(&domain.id),
+            _ => None,
+        };
+        if let Some(ref domain_id) = self.domain_id {
+            if &token.user.domain.id != domain_id && scope_domain_id != Some(domain_id) {
+                return false;
+            }
+        }
+        if self.domain_scope_id.is_some() && self.domain_scope_id.as_ref() != scope_domain_id {
+            return false;
+        }
+
+        if let Some(ref role_id) = self.role_id {
+            if !token.roles.iter().any(|role| &role.id == role_id) {
+                return false;
+            }
+        }
+
+        let project_id = token.project.as_ref().map(|project| &project.id);
+        let trust_id = token.trust.as_ref().map(|trust| &trust.id);
+        if !optional_matches(&self.project_id, project_id)
+            || !optional_matches(&self.trust_id, trust_id)
+            || !optional_matches(&self.audit_id, token.audit_ids.first())
+            || !optional_matches(&self.audit_chain_id, token.audit_ids.last())
+            // OAuth1 information is not available in tokens.
+            || self.consumer_id.is_some()
+            || self.access_token_id.is_some()
+        {
+            return false;
+        }
+
+        match self.expires_at {
+            Some(expires_at) => truncate(expires_at) == truncate(token.expires_at),
+            None => true,
+        }
+    }
+}
+
+impl RevocationEventsRoot {
+    /// Find the first event that revokes the token.
+    #[inline]
+    pub fn find(&self, token: &Token) -> Option<&RevocationEvent> {
+        find_revocation(&self.events, token)
+    }
+
+    /// Whether the token is revoked by any of the events.
+    #[inline]
+    pub fn is_revoked(&self, token: &Token) -> bool {
+        self.find(token).is_some()
+    }
+}
+
+/// Find the first event that revokes the token.
+pub fn find_revocation<'e, I>(events: I, token: &Token) -> Option<&'e RevocationEvent>
+where
+    I: IntoIterator<Item = &'e RevocationEvent>,
+{
+    events.into_iter().find(|event| event.matches(token))
+}
+
+#[inline]
+fn optional_matches(expected: &Option<String>, actual: Option<&String>) -> bool {
+    expected.is_none() || expected.as_ref() == actual
+}
+
+#[inline]
+fn truncate(value: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+    value.with_nanosecond(0).unwrap_or(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::TokenRoot;
+    use super::*;
+
+    const TOKEN: &str = r#"
+{
+    "token": {
+        "methods": [
+            "token"
+        ],
+        "roles": [
+            {
+                "id": "51cc68287d524c759f47c811e6463340",
+                "name": "admin"
+            }
+        ],
+        "expires_at": "2015-11-07T02:58:43.578887Z",
+        "project": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "a6944d763bf64ee6a275f1263fae0352",
+            "name": "admin"
+        },
+        "user": {
+            "domain": {
+                "id": "users",
+                "name": "Users"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin"
+        },
+        "audit_ids": [
+            "3T2dc1CGQxyJsHdDu1xkcw",
+            "lC2Wj1jbQe-dLjLyOx4qPQ"
+        ],
+        "issued_at": "2015-11-07T01:58:43.578929Z"
+    }
+}"#;
+
+    const EVENTS: &str = r#"
+{
+    "events": [
+        {
+            "issued_before": "2015-11-07T01:00:00.000000Z",
+            "user_id": "ee4dfb6e5540447cb3741905149d9b6e"
+        },
+        {
+            "issued_before": "2015-11-07T03:00:00.000000Z",
+            "revoked_at": "2015-11-07T03:00:00.000000Z",
+            "project_id": "a6944d763bf64ee6a275f1263fae0352",
+            "role_id": "51cc68287d524c759f47c811e6463340"
+        }
+    ],
+    "links": {
+        "self": "http://example.com/identity/v3/OS-REVOKE/events",
+        "next": null,
+        "previous": null
+    }
+}"#;
+
+    fn token() -> Token {
+        serde_json::from_str::<TokenRoot>(TOKEN).unwrap().token
+    }
+
+    fn event(value: serde_json::Value) -> RevocationEvent {
+        let mut value = value;
+        if value.get("issued_before").is_none() {
+            value["issued_before"] = "2015-11-07T02:00:00Z".into();
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_events() {
+        let root: RevocationEventsRoot = serde_json::from_str(EVENTS).unwrap();
+        assert_eq!(root.events.len(), 2);
+        let token = token();
+        assert!(!root.events[0].matches(&token));
+        assert!(root.events[1].matches(&token));
+        assert!(root.is_revoked(&token));
+        assert_eq!(
+            root.find(&token).unwrap().role_id.as_ref().unwrap(),
+            "51cc68287d524c759f47c811e6463340"
+        );
+        assert!(find_revocation(&root.events[..1], &token).is_none());
+    }
+
+    #[test]
+    fn test_matches() {
+        let token = token();
+        for value in vec![
+            serde_json::json!({}),
+            serde_json::json!({"issued_before": "2015-11-07T01:58:43.578929Z"}),
+            serde_json::json!({"user_id": "ee4dfb6e5540447cb3741905149d9b6e"}),
+            serde_json::json!({"domain_id": "users"}),
+            serde_json::json!({"domain_id": "default"}),
+            serde_json::json!({"domain_scope_id": "default"}),
+            serde_json::json!({"audit_id": "3T2dc1CGQxyJsHdDu1xkcw"}),
+            serde_json::json!({"audit_chain_id": "lC2Wj1jbQe-dLjLyOx4qPQ"}),
+            serde_json::json!({"expires_at": "2015-11-07T02:58:43Z"}),
+            serde_json::json!({
+                "project_id": "a6944d763bf64ee6a275f1263fae0352",
+                "user_id": "ee4dfb6e5540447cb3741905149d9b6e"
+            }),
+        ] {
+            assert!(event(value.clone()).matches(&token), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_does_not_match() {
+        let token = token();
+        for value in vec![
+            serde_json::json!({"issued_before": "2015-11-07T01:58:43Z"}),
+            serde_json::json!({"user_id": "other"}),
+            serde_json::json!({"domain_id": "other"}),
+            serde_json::json!({"domain_scope_id": "users"}),
+            serde_json::json!({"role_id": "other"}),
+            serde_json::json!({"trust_id": "fe0aef"}),
+            serde_json::json!({"audit_id": "lC2Wj1jbQe-dLjLyOx4qPQ"}),
+            serde_json::json!({"audit_chain_id": "3T2dc1CGQxyJsHdDu1xkcw"}),
+            serde_json::json!({"expires_at": "2015-11-07T02:58:44Z"}),
+            serde_json::json!({"OS-OAUTH1:consumer_id": "abcd"}),
+            serde_json::json!({
+                "project_id": "a6944d763bf64ee6a275f1263fae0352",
+                "user_id": "other"
+            }),
+        ] {
+            assert!(!event(value.clone()).matches(&token), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,590 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Roles, role assignments and role inferences JSON structures and protocol bits.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::super::common::{IdAndName, ResourceLinks};
+use super::{Token, TokenSystem};
+
+/// A role.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Role {
+    pub id: String,
+    pub name: String,
+    /// Owning domain for domain-specific roles.
+    #[serde(default)]
+    pub domain_id: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A role response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RoleRoot {
+    pub role: Role,
+}
+
+/// A list of roles.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RolesRoot {
+    pub roles: Vec<Role>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A domain in a role assignment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AssignmentDomain {
+    pub id: String,
+    /// Only returned with `include_names`.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// A role, user, group or project in a role assignment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AssignmentRef {
+    pub id: String,
+    /// Only returned with `include_names`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Only returned with `include_names` (and only for domain-specific roles).
+    #[serde(default)]
+    pub domain: Option<AssignmentDomain>,
+}
+
+/// A scope of a role assignment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AssignmentScope {
+    #[serde(default)]
+    pub project: Option<AssignmentRef>,
+    #[serde(default)]
+    pub domain: Option<AssignmentDomain>,
+    #[serde(default)]
+    pub system: Option<TokenSystem>,
+    /// Set to `projects` for assignments inherited by the projects of the scope.
+    #[serde(rename = "OS-INHERIT:inherited_to", default)]
+    pub inherited_to: Option<String>,
+}
+
+/// Links of a role assignment.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AssignmentLinks {
+    /// The direct assignment this assignment originates from.
+    #[serde(default)]
+    pub assignment: Option<Url>,
+    /// Group membership for effective assignments derived from a group.
+    #[serde(default)]
+    pub membership: Option<Url>,
+}
+
+/// A role assignment.
+///
+/// Exactly one of `user` and `group` is set.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RoleAssignment {
+    pub role: AssignmentRef,
+    pub scope: AssignmentScope,
+    #[serde(default)]
+    pub user: Option<AssignmentRef>,
+    #[serde(default)]
+    pub group: Option<AssignmentRef>,
+    #[serde(default)]
+    pub links: AssignmentLinks,
+}
+
+/// A list of role assignments.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RoleAssignmentsRoot {
+    pub role_assignments: Vec<RoleAssignment>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Query filters for listing role assignments.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RoleAssignmentFilter {
+    /// Expand group memberships and inheritance into user assignments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_names: Option<bool>,
+    /// Include assignments on the subtree of the project given in `scope_project_id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_subtree: Option<bool>,
+    #[serde(rename = "group.id", skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(rename = "role.id", skip_serializing_if = "Option::is_none")]
+    pub role_id: Option<String>,
+    #[serde(rename = "scope.domain.id", skip_serializing_if = "Option::is_none")]
+    pub scope_domain_id: Option<String>,
+    #[serde(rename = "scope.project.id", skip_serializing_if = "Option::is_none")]
+    pub scope_project_id: Option<String>,
+    /// Only `all` is supported.
+    #[serde(rename = "scope.system", skip_serializing_if = "Option::is_none")]
+    pub scope_system: Option<String>,
+    #[serde(
+        rename = "scope.OS-INHERIT:inherited_to",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scope_inherited_to: Option<String>,
+    #[serde(rename = "user.id", skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+/// A prior role and all roles it implies.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RoleInference {
+    pub prior_role: IdAndName,
+    pub implies: Vec<IdAndName>,
+}
+
+/// A list of role inferences.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RoleInferencesRoot {
+    pub role_inferences: Vec<RoleInference>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A single implied role rule.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImpliedRole {
+    pub prior_role: IdAndName,
+    pub implies: IdAndName,
+}
+
+/// A single implied role rule response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImpliedRoleRoot {
+    pub role_inference: ImpliedRole,
+}
+
+/// An in-memory graph of implied roles.
+///
+/// Roles are identified by their IDs. Expansion is safe even if the rules contain cycles.
+#[derive(Clone, Debug, Default)]
+pub struct ImpliedRoles {
+    rules: BTreeMap<String, BTreeSet<String>>,
+    names: HashMap<String, String>,
+}
+
+impl AssignmentScope {
+    /// Whether the assignment is inherited by the projects of the scope.
+    pub fn is_inherited(&self) -> bool {
+        self.inherited_to.is_some()
+    }
+}
+
+impl RoleAssignment {
+    /// Whether the assignment is derived from a group membership.
+    pub fn is_from_group(&self) -> bool {
+        self.links.membership.is_some()
+    }
+}
+
+impl ImpliedRoles {
+    /// An empty graph.
+    pub fn new() -> ImpliedRoles {
+        ImpliedRoles::default()
+    }
+
+    /// Build a graph from a list of role inferences.
+    pub fn from_inferences(inferences: &[RoleInference]) -> ImpliedRoles {
+        let mut result = ImpliedRoles::new();
+        for inference in inferences {
+            for implied in &inference.implies {
+                result.add(&inference.prior_role, implied);
+            }
+        }
+        result
+    }
+
+    /// Add a rule that `prior` implies `implied`.
+    pub fn add(&mut self, prior: &IdAndName, implied: &IdAndName) {
+        for role in &[prior, implied] {
+            let _ = self.names.insert(role.id.clone(), role.name.clone());
+        }
+        self.add_ids(&prior.id, &implied.id);
+    }
+
+    /// Add a rule that the role `prior_id` implies the role `implied_id`.
+    pub fn add_ids<S1, S2>(&mut self, prior_id: S1, implied_id: S2)
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        let _ = self
+            .rules
+            .entry(prior_id.into())
+            .or_default()
+            .insert(implied_id.into());
+    }
+
+    /// Remember names of roles from a role list.
+    pub fn add_names(&mut self, roles: &RolesRoot) {
+        for role in &roles.roles {
+            let _ = self.names.insert(role.id.clone(), role.name.clone());
+        }
+    }
+
+    /// Name of a role, if known.
+    pub fn name(&self, id: &str) -> Option<&str> {
+        self.names.get(id).map(String::as_str)
+    }
+
+    /// Roles directly implied by the role.
+    pub fn implied_by(&self, id: &str) -> impl Iterator<Item = &str> {
+        self.rules
+            .get(id)
+            .into_iter()
+            .flat_map(|implied| implied.iter().map(String::as_str))
+    }
+
+    /// Expand role IDs to the effective set of roles including all implied ones.
+    pub fn expand<'a, I>(&self, roles: I) -> BTreeSet<String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut result = BTreeSet::new();
+        let mut stack = roles.into_iter().collect::<Vec<_>>();
+        while let Some(role) = stack.pop() {
+            if result.insert(role.to_string()) {
+                stack.extend(self.implied_by(role));
+            }
+        }
+        result
+    }
+
+    /// Expand roles of a token to the effective set of role IDs.
+    ///
+    /// Keystone already expands implied roles when issuing tokens, this is useful when the
+    /// graph contains rules that the token issuer does not know about.
+    pub fn expand_token(&self, token: &Token) -> BTreeSet<String> {
+        self.expand(token.roles.iter().map(|role| role.id.as_str()))
+    }
+
+    /// Find a cycle in the graph.
+    ///
+    /// Returns the role IDs forming the cycle with the first role repeated at the end.
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut finished = BTreeSet::new();
+        for start in self.rules.keys() {
+            let mut path = Vec::new();
+            if let Some(cycle) = self.visit(start, &mut path, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn visit<'a>(
+        &'a self,
+        role: &'a str,
+        path: &mut Vec<&'a str>,
+        finished: &mut BTreeSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(pos) = path.iter().position(|item| *item == role) {
+            let mut cycle = path[pos..]
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>();
+            cycle.push(role.to_string());
+            return Some(cycle);
+        }
+        if finished.contains(role) {
+            return None;
+        }
+
+        path.push(role);
+        for implied in self.implied_by(role) {
+            if let Some(cycle) = self.visit(implied, path, finished) {
+                return Some(cycle);
+            }
+        }
+        let _ = path.pop();
+        let _ = finished.insert(role);
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::*;
+
+    const ROLE_ASSIGNMENTS: &str = r#"
+{
+    "role_assignments": [
+        {
+            "links": {
+                "assignment": "http://example.com/identity/v3/domains/161718/users/313233/roles/123456"
+            },
+            "role": {
+                "id": "123456",
+                "name": "admin"
+            },
+            "scope": {
+                "domain": {
+                    "id": "161718",
+                    "name": "Default"
+                }
+            },
+            "user": {
+                "domain": {
+                    "id": "161718",
+                    "name": "Default"
+                },
+                "id": "313233",
+                "name": "alice"
+            }
+        },
+        {
+            "links": {
+                "assignment": "http://example.com/identity/v3/projects/456789/groups/101112/roles/123456",
+                "membership": "http://example.com/identity/v3/groups/101112/users/313233"
+            },
+            "role": {
+                "id": "123456"
+            },
+            "scope": {
+                "project": {
+                    "id": "456789"
+                }
+            },
+            "user": {
+                "id": "313233"
+            }
+        },
+        {
+            "links": {
+                "assignment": "http://example.com/identity/v3/OS-INHERIT/domains/161718/groups/101112/roles/654321/inherited_to_projects"
+            },
+            "role": {
+                "id": "654321"
+            },
+            "scope": {
+                "domain": {
+                    "id": "161718"
+                },
+                "OS-INHERIT:inherited_to": "projects"
+            },
+            "group": {
+                "id": "101112"
+            }
+        },
+        {
+            "links": {
+                "assignment": "http://example.com/identity/v3/system/users/313233/roles/123456"
+            },
+            "role": {
+                "id": "123456"
+            },
+            "scope": {
+                "system": {
+                    "all": true
+                }
+            },
+            "user": {
+                "id": "313233"
+            }
+        }
+    ],
+    "links": {
+        "self": "http://example.com/identity/v3/role_assignments?effective",
+        "previous": null,
+        "next": null
+    }
+}"#;
+
+    const ROLE_INFERENCES: &str = r#"
+{
+    "role_inferences": [
+        {
+            "prior_role": {
+                "id": "1acd3c",
+                "links": {
+                    "self": "http://example.com/identity/v3/roles/1acd3c"
+                },
+                "name": "admin"
+            },
+            "implies": [
+                {
+                    "id": "3602ce",
+                    "links": {
+                        "self": "http://example.com/identity/v3/roles/3602ce"
+                    },
+                    "name": "member"
+                }
+            ]
+        },
+        {
+            "prior_role": {
+                "id": "3602ce",
+                "name": "member"
+            },
+            "implies": [
+                {
+                    "id": "0e1b2c",
+                    "name": "reader"
+                },
+                {
+                    "id": "9f8e7d",
+                    "name": "load-balancer_member"
+                }
+            ]
+        }
+    ]
+}"#;
+
+    const IMPLIED_ROLE: &str = r#"
+{
+    "role_inference": {
+        "prior_role": {
+            "id": "42c764",
+            "name": "prior role name"
+        },
+        "implies": {
+            "id": "066fbf",
+            "name": "implied role1 name"
+        }
+    },
+    "links": {
+        "self": "http://example.com/identity/v3/roles/42c764/implies/066fbf"
+    }
+}"#;
+
+    fn role(id: &str, name: &str) -> IdAndName {
+        IdAndName {
+            id: id.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_role_assignments() {
+        let root: RoleAssignmentsRoot = serde_json::from_str(ROLE_ASSIGNMENTS).unwrap();
+        assert_eq!(root.role_assignments.len(), 4);
+
+        let first = &root.role_assignments[0];
+        assert_eq!(first.role.name.as_ref().unwrap(), "admin");
+        let user = first.user.as_ref().unwrap();
+        assert_eq!(
+            user.domain.as_ref().unwrap().name.as_ref().unwrap(),
+            "Default"
+        );
+        assert_eq!(first.scope.domain.as_ref().unwrap().id, "161718");
+        assert!(!first.is_from_group());
+
+        let second = &root.role_assignments[1];
+        assert!(second.is_from_group());
+        assert!(second.role.name.is_none());
+        assert_eq!(second.scope.project.as_ref().unwrap().id, "456789");
+
+        let third = &root.role_assignments[2];
+        assert!(third.user.is_none());
+        assert_eq!(third.group.as_ref().unwrap().id, "101112");
+        assert!(third.scope.is_inherited());
+
+        let fourth = &root.role_assignments[3];
+        assert!(fourth.scope.system.unwrap().all);
+        assert!(!fourth.scope.is_inherited());
+    }
+
+    #[test]
+    fn test_role_assignment_filter() {
+        let filter = RoleAssignmentFilter {
+            effective: Some(true),
+            include_names: Some(true),
+            include_subtree: Some(true),
+            scope_project_id: Some("456789".to_string()),
+            user_id: Some("313233".to_string()),
+            ..RoleAssignmentFilter::default()
+        };
+        test::compare(
+            r#"{"effective": true, "include_names": true, "include_subtree": true,
+                "scope.project.id": "456789", "user.id": "313233"}"#,
+            filter,
+        );
+    }
+
+    #[test]
+    fn test_role_inferences() {
+        let root: RoleInferencesRoot = serde_json::from_str(ROLE_INFERENCES).unwrap();
+        let graph = ImpliedRoles::from_inferences(&root.role_inferences);
+        assert_eq!(graph.name("0e1b2c"), Some("reader"));
+        assert_eq!(
+            graph.implied_by("3602ce").collect::<Vec<_>>(),
+            vec!["0e1b2c", "9f8e7d"]
+        );
+        assert_eq!(
+            graph.expand(vec!["1acd3c"]).into_iter().collect::<Vec<_>>(),
+            vec!["0e1b2c", "1acd3c", "3602ce", "9f8e7d"]
+        );
+        assert_eq!(graph.expand(vec!["0e1b2c", "unknown"]).len(), 2);
+        assert!(graph.find_cycle().is_none());
+    }
+
+    #[test]
+    fn test_implied_role() {
+        let root: ImpliedRoleRoot = serde_json::from_str(IMPLIED_ROLE).unwrap();
+        let mut graph = ImpliedRoles::new();
+        graph.add(
+            &root.role_inference.prior_role,
+            &root.role_inference.implies,
+        );
+        assert_eq!(
+            graph.implied_by("42c764").collect::<Vec<_>>(),
+            vec!["066fbf"]
+        );
+        assert_eq!(graph.name("42c764"), Some("prior role name"));
+    }
+
+    #[test]
+    fn test_implied_roles_cycle() {
+        let mut graph = ImpliedRoles::new();
+        graph.add(&role("a", "admin"), &role("m", "member"));
+        graph.add(&role("m", "member"), &role("r", "reader"));
+        graph.add_ids("r", "x");
+        assert!(graph.find_cycle().is_none());
+
+        graph.add_ids("x", "m");
+        assert_eq!(
+            graph.find_cycle().unwrap(),
+            vec!["m", "r", "x", "m"]
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        );
+        // Expansion still terminates.
+        assert_eq!(graph.expand(vec!["a"]).len(), 4);
+    }
+
+    #[test]
+    fn test_implied_roles_self_cycle() {
+        let mut graph = ImpliedRoles::new();
+        graph.add_ids("a", "a");
+        assert_eq!(
+            graph.find_cycle().unwrap(),
+            vec!["a".to_string(), "a".to_string()]
+        );
+    }
+}
@@ -0,0 +1,815 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Token validation structures and authentication headers.
+//!
+//! Services behind an authentication middleware receive information about validated tokens in
+//! a set of headers (the same as produced by `keystonemiddleware`). `AuthHeaders` converts
+//! validated tokens into these headers and parses them back.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{CatalogRecord, Token, TokenScope};
+
+/// Path (relative to the identity endpoint) for token validation.
+pub const TOKENS_PATH: &str = "auth/tokens";
+
+/// Header carrying the token used to authenticate a request.
+pub const AUTH_TOKEN_HEADER: &str = "X-Auth-Token";
+
+/// Header carrying the token to validate (and the issued token in responses).
+pub const SUBJECT_TOKEN_HEADER: &str = "X-Subject-Token";
+
+/// Header carrying the token of a service acting on behalf of a user.
+pub const SERVICE_TOKEN_HEADER: &str = "X-Service-Token";
+
+/// Header carrying the service catalog of the user token.
+pub const SERVICE_CATALOG_HEADER: &str = "X-Service-Catalog";
+
+/// Deprecated headers that must be removed from incoming requests as well.
+const DEPRECATED_HEADERS: &[&str] = &[
+    "X-Tenant-Id",
+    "X-Tenant-Name",
+    "X-Tenant",
+    "X-User",
+    "X-Role",
+];
+
+/// Query for token validation.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct TokenValidationQuery {
+    /// Do not include the catalog into the response.
+    #[serde(skip_serializing_if = "super::is_false")]
+    pub nocatalog: bool,
+    /// Allow validating expired tokens (within the window configured in Keystone).
+    #[serde(skip_serializing_if = "super::is_false")]
+    pub allow_expired: bool,
+}
+
+/// Status of the token validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdentityStatus {
+    /// The token is valid.
+    Confirmed,
+    /// The token is missing or invalid.
+    Invalid,
+}
+
+/// Identity information of a validated token as passed in the headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenIdentity {
+    pub user_id: String,
+    pub user_name: String,
+    pub user_domain_id: String,
+    pub user_domain_name: String,
+    pub project_id: Option<String>,
+    pub project_name: Option<String>,
+    pub project_domain_id: Option<String>,
+    pub project_domain_name: Option<String>,
+    pub domain_id: Option<String>,
+    pub domain_name: Option<String>,
+    /// Whether the token is scoped to the whole system (not passed for service tokens).
+    pub system_scope: bool,
+    /// Role names.
+    pub roles: Vec<String>,
+    /// Whether the project is the admin project (not passed for service tokens).
+    pub is_admin_project: bool,
+}
+
+/// Authentication headers set by an authentication middleware.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthHeaders {
+    /// Status of the user token.
+    pub status: IdentityStatus,
+    /// Identity of the user (only if confirmed).
+    pub user: Option<TokenIdentity>,
+    /// Status of the service token (if one was provided).
+    pub service_status: Option<IdentityStatus>,
+    /// Identity of the service (only if confirmed).
+    pub service: Option<TokenIdentity>,
+    /// Service catalog of the user token in the Identity V2 JSON format (if present).
+    pub service_catalog: Option<String>,
+}
+
+/// An error when parsing authentication headers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    /// A required header is missing.
+    Missing(String),
+    /// A header has an invalid value.
+    InvalidValue { header: String, value: String },
+}
+
+struct HeaderNames {
+    status: &'static str,
+    user_id: &'static str,
+    user_name: &'static str,
+    user_domain_id: &'static str,
+    user_domain_name: &'static str,
+    project_id: &'static str,
+    project_name: &'static str,
+    project_domain_id: &'static str,
+    project_domain_name: &'static str,
+    domain_id: &'static str,
+    domain_name: &'static str,
+    roles: &'static str,
+    system_scope: Option<&'static str>,
+    is_admin_project: Option<&'static str>,
+}
+
+const USER_HEADERS: HeaderNames = HeaderNames {
+    status: "X-Identity-Status",
+    user_id: "X-User-Id",
+    user_name: "X-User-Name",
+    user_domain_id: "X-User-Domain-Id",
+    user_domain_name: "X-User-Domain-Name",
+    project_id: "X-Project-Id",
+    project_name: "X-Project-Name",
+    project_domain_id: "X-Project-Domain-Id",
+    project_domain_name: "X-Project-Domain-Name",
+    domain_id: "X-Domain-Id",
+    domain_name: "X-Domain-Name",
+    roles: "X-Roles",
+    system_scope: Some("X-System-Scope"),
+    is_admin_project: Some("X-Is-Admin-Project"),
+};
+
+const SERVICE_HEADERS: HeaderNames = HeaderNames {
+    status: "X-Service-Identity-Status",
+    user_id: "X-Service-User-Id",
+    user_name: "X-Service-User-Name",
+    user_domain_id: "X-Service-User-Domain-Id",
+    user_domain_name: "X-Service-User-Domain-Name",
+    project_id: "X-Service-Project-Id",
+    project_name: "X-Service-Project-Name",
+    project_domain_id: "X-Service-Project-Domain-Id",
+    project_domain_name: "X-Service-Project-Domain-Name",
+    domain_id: "X-Service-Domain-Id",
+    domain_name: "X-Service-Domain-Name",
+    roles: "X-Service-Roles",
+    system_scope: None,
+    is_admin_project: None,
+};
+
+impl HeaderNames {
+    fn all(&self) -> Vec<&'static str> {
+        let mut result = vec![
+            self.status,
+            self.user_id,
+            self.user_name,
+            self.user_domain_id,
+            self.user_domain_name,
+            self.project_id,
+            self.project_name,
+            self.project_domain_id,
+            self.project_domain_name,
+            self.domain_id,
+            self.domain_name,
+            self.roles,
+        ];
+        result.extend(self.system_scope);
+        result.extend(self.is_admin_project);
+        result
+    }
+}
+
+impl IdentityStatus {
+    /// String representation of the status.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IdentityStatus::Confirmed => "Confirmed",
+            IdentityStatus::Invalid => "Invalid",
+        }
+    }
+}
+
+impl fmt::Display for IdentityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for IdentityStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<IdentityStatus, ()> {
+        match s {
+            "Confirmed" => Ok(IdentityStatus::Confirmed),
+            "Invalid" => Ok(IdentityStatus::Invalid),
+            _ => Err(()),
+        }
+    }
+}
+
+impl<'a> From<&'a Token> for TokenIdentity {
+    fn from(token: &'a Token) -> TokenIdentity {
+        let mut result = TokenIdentity {
+            user_id: token.user.id.clone(),
+            user_name: token.user.name.clone(),
+            user_domain_id: token.user.domain.id.clone(),
+            user_domain_name: token.user.domain.name.clone(),
+            roles: token.roles.iter().map(|role| role.name.clone()).collect(),
+            is_admin_project: token.is_admin_project.unwrap_or(true),
+            ..TokenIdentity::default()
+        };
+        match token.scope() {
+            TokenScope::Project(project) => {
+                result.project_id = Some(project.id.clone());
+                result.project_name = Some(project.name.clone());
+                result.project_domain_id = Some(project.domain.id.clone());
+                result.project_domain_name = Some(project.domain.name.clone());
+            }
+            TokenScope::Domain(domain) => {
+                result.domain_id = Some(domain.id.clone());
+                result.domain_name = Some(domain.name.clone());
+            }
+            TokenScope::System(system) => result.system_scope = system.all,
+            TokenScope::Unscoped => (),
+        }
+        result
+    }
+}
+
+impl TokenIdentity {
+    fn to_headers(&self, names: &HeaderNames, result: &mut Vec<(String, String)>) {
+        let mut add = |name: &str, value: &str| result.push((name.to_string(), value.to_string()));
+        add(names.user_id, &self.user_id);
+        add(names.user_name, &self.user_name);
+        add(names.user_domain_id, &self.user_domain_id);
+        add(names.user_domain_name, &self.user_domain_name);
+        for (name, value) in &[
+            (names.project_id, &self.project_id),
+            (names.project_name, &self.project_name),
+            (names.project_domain_id, &self.project_domain_id),
+            (names.project_domain_name, &self.project_domain_name),
+            (names.domain_id, &self.domain_id),
+            (names.domain_name, &self.domain_name),
+        ] {
+            if let Some(value) = value {
+                add(name, value);
+            }
+        }
+        add(names.roles, &self.roles.join(","));
+        if let Some(name) = names.system_scope {
+            if self.system_scope {
+                add(name, "all");
+            }
+        }
+        if let Some(name) = names.is_admin_project {
+            add(
+                name,
+                if self.is_admin_project {
+                    "True"
+                } else {
+                    "False"
+                },
+            );
+        }
+    }
+
+    fn from_headers(
+        headers: &HashMap<String, &str>,
+        names: &HeaderNames,
+    ) -> Result<TokenIdentity, HeaderError> {
+        let get = |name: &str| {
+            headers
+                .get(&name.to_lowercase())
+                .map(|value| value.to_string())
+        };
+        let required = |name: &str| get(name).ok_or_else(|| HeaderError::Missing(name.into()));
+
+        let invalid = |header: &str, value: String| HeaderError::InvalidValue {
+            header: header.to_string(),
+            value,
+        };
+
+        let mut system_scope = false;
+        if let Some(name) = names.system_scope {
+            match get(name) {
+                Some(ref value) if value == "all" => system_scope = true,
+                Some(value) => return Err(invalid(name, value)),
+                None => (),
+            }
+        }
+
+        let mut is_admin_project = true;
+        if let Some(name) = names.is_admin_project {
+            if let Some(value) = get(name) {
+                is_admin_project = match value.to_lowercase().as_str() {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(invalid(name, value)),
+                };
+            }
+        }
+
+        Ok(TokenIdentity {
+            user_id: required(names.user_id)?,
+            user_name: required(names.user_name)?,
+            user_domain_id: required(names.user_domain_id)?,
+            user_domain_name: required(names.user_domain_name)?,
+            project_id: get(names.project_id),
+            project_name: get(names.project_name),
+            project_domain_id: get(names.project_domain_id),
+            project_domain_name: get(names.project_domain_name),
+            domain_id: get(names.domain_id),
+            domain_name: get(names.domain_name),
+            system_scope,
+            roles: get(names.roles)
+                .map(|roles| {
+                    roles
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|role| !role.is_empty())
+                        .map(From::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            is_admin_project,
+        })
+    }
+}
+
+impl AuthHeaders {
+    /// Authentication headers for a validated user token.
+    ///
+    /// `None` means that the token was missing or invalid.
+    pub fn new(user: Option<&Token>) -> AuthHeaders {
+        AuthHeaders {
+            status: status(user.is_some()),
+            user: user.map(From::from),
+            service_status: None,
+            service: None,
+            service_catalog: user
+                .filter(|token| !token.catalog.is_empty())
+                .map(|token| v2_catalog(&token.catalog).to_string()),
+        }
+    }
+
+    /// Add a validated service token.
+    ///
+    /// `None` means that a service token was provided but is invalid.
+    pub fn with_service(mut self, service: Option<&Token>) -> AuthHeaders {
+        self.service_status = Some(status(service.is_some()));
+        // System scope and admin project information are not passed for service tokens.
+        self.service = service.map(|token| TokenIdentity {
+            system_scope: false,
+            is_admin_project: true,
+            ..TokenIdentity::from(token)
+        });
+        self
+    }
+
+    /// Convert into a list of headers.
+    pub fn to_headers(&self) -> Vec<(String, String)> {
+        let mut result = vec![(USER_HEADERS.status.to_string(), self.status.to_string())];
+        if let Some(ref user) = self.user {
+            user.to_headers(&USER_HEADERS, &mut result);
+        }
+        if let Some(ref catalog) = self.service_catalog {
+            result.push((SERVICE_CATALOG_HEADER.to_string(), catalog.clone()));
+        }
+        if let Some(status) = self.service_status {
+            result.push((SERVICE_HEADERS.status.to_string(), status.to_string()));
+        }
+        if let Some(ref service) = self.service {
+            service.to_headers(&SERVICE_HEADERS, &mut result);
+        }
+        result
+    }
+
+    /// Parse authentication headers (header names are case-insensitive).
+    ///
+    /// Identities are only parsed for confirmed tokens.
+    pub fn from_headers<'a, I, K>(headers: I) -> Result<AuthHeaders, HeaderError>
+    where
+        I: IntoIterator<Item = (K, &'a str)>,
+        K: AsRef<str>,
+    {
+        let headers = headers
+            .into_iter()
+            .map(|(key, value)| (key.as_ref().to_lowercase(), value))
+            .collect::<HashMap<_, _>>();
+
+        let parse_status = |name: &str| {
+            headers
+                .get(&name.to_lowercase())
+                .map(|value| {
+                    value.parse().map_err(|()| HeaderError::InvalidValue {
+                        header: name.to_string(),
+                        value: value.to_string(),
+                    })
+                })
+                .transpose()
+        };
+
+        let status = parse_status(USER_HEADERS.status)?
+            .ok_or_else(|| HeaderError::Missing(USER_HEADERS.status.to_string()))?;
+        let (user, service_catalog) = if status == IdentityStatus::Confirmed {
+            (
+                Some(TokenIdentity::from_headers(&headers, &USER_HEADERS)?),
+                headers
+                    .get(&SERVICE_CATALOG_HEADER.to_lowercase())
+                    .map(|value| value.to_string()),
+            )
+        } else {
+            (None, None)
+        };
+        let service_status = parse_status(SERVICE_HEADERS.status)?;
+        let service = if service_status == Some(IdentityStatus::Confirmed) {
+            Some(TokenIdentity::from_headers(&headers, &SERVICE_HEADERS)?)
+        } else {
+            None
+        };
+
+        Ok(AuthHeaders {
+            status,
+            user,
+            service_status,
+            service,
+            service_catalog,
+        })
+    }
+}
+
+/// Whether the header is set by an authentication middleware.
+///
+/// Such headers must be removed from incoming requests before validating tokens, otherwise
+/// clients could forge them.
+pub fn is_auth_header(name: &str) -> bool {
+    USER_HEADERS
+        .all()
+        .into_iter()
+        .chain(SERVICE_HEADERS.all())
+        .chain(Some(SERVICE_CATALOG_HEADER))
+        .chain(DEPRECATED_HEADERS.iter().cloned())
+        .any(|header| header.eq_ignore_ascii_case(name))
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::Missing(name) => write!(f, "Missing required header {}", name),
+            HeaderError::InvalidValue { header, value } => {
+                write!(f, "Invalid value '{}' of header {}", value, header)
+            }
+        }
+    }
+}
+
+impl Error for HeaderError {}
+
+/// Convert the catalog into the Identity V2 format the same way as keystonemiddleware.
+fn v2_catalog(catalog: &[CatalogRecord]) -> Value {
+    let services = catalog
+        .iter()
+        .map(|record| {
+            let mut service = Map::new();
+            let _ = service.insert("type".into(), record.service_type.clone().into());
+            if let Some(ref name) = record.name {
+                let _ = service.insert("name".into(), name.clone().into());
+            }
+
+            let mut regions: Vec<(&str, Map<String, Value>)> = Vec::new();
+            for endpoint in &record.endpoints {
+                let index = match regions
+                    .iter()
+                    .position(|(region, _)| *region == endpoint.region)
+                {
+                    Some(index) => index,
+                    None => {
+                        let mut region = Map::new();
+                        if !endpoint.region.is_empty() {
+                            let _ = region.insert("region".into(), endpoint.region.clone().into());
+                        }
+                        regions.push((&endpoint.region, region));
+                        regions.len() - 1
+                    }
+                };
+                let _ = regions[index].1.insert(
+                    format!("{}URL", endpoint.interface.as_str().to_lowercase()),
+                    endpoint.url.clone().into(),
+                );
+            }
+
+            let endpoints = regions
+                .into_iter()
+                .map(|(_, region)| Value::Object(region))
+                .collect::<Vec<_>>();
+            let _ = service.insert("endpoints".into(), endpoints.into());
+            Value::Object(service)
+        })
+        .collect::<Vec<_>>();
+    Value::Array(services)
+}
+
+#[inline]
+fn status(confirmed: bool) -> IdentityStatus {
+    if confirmed {
+        IdentityStatus::Confirmed
+    } else {
+        IdentityStatus::Invalid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::TokenRoot;
+    use super::*;
+
+    const TOKEN: &str = r#"
+{
+    "token": {
+        "methods": [
+            "password"
+        ],
+        "roles": [
+            {
+                "id": "51cc68287d524c759f47c811e6463340",
+                "name": "admin"
+            },
+            {
+                "id": "9fe2ff9ee4384b1894a90878d3e92bab",
+                "name": "member"
+            }
+        ],
+        "expires_at": "2015-11-07T02:58:43.578887Z",
+        "project": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "a6944d763bf64ee6a275f1263fae0352",
+            "name": "admin"
+        },
+        "is_domain": false,
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "ee4dfb6e5540447cb3741905149d9b6e",
+            "name": "admin"
+        },
+        "audit_ids": [
+            "3T2dc1CGQxyJsHdDu1xkcw"
+        ],
+        "issued_at": "2015-11-07T01:58:43.578929Z"
+    }
+}"#;
+
+    const SERVICE_TOKEN: &str = r#"
+{
+    "token": {
+        "methods": [
+            "password"
+        ],
+        "roles": [
+            {
+                "id": "2ea3bf8d4a5a4e8e9f1ac5f3d8ae3f53",
+                "name": "service"
+            }
+        ],
+        "expires_at": "2015-11-07T02:58:43.578887Z",
+        "system": {
+            "all": true
+        },
+        "user": {
+            "domain": {
+                "id": "default",
+                "name": "Default"
+            },
+            "id": "1e3a5b2d7c8f4d6e9a0b1c2d3e4f5a6b",
+            "name": "nova"
+        },
+        "is_admin_project": false,
+        "issued_at": "2015-11-07T01:58:43.578929Z"
+    }
+}"#;
+
+    const CATALOG: &str = r#"
+[
+    {
+        "endpoints": [
+            {
+                "id": "39dc322ce86c4111b4f06c2eeae0841b",
+                "interface": "public",
+                "region": "RegionOne",
+                "url": "http://localhost:5000"
+            },
+            {
+                "id": "ec642f27474842e78bf059f6c48f4e99",
+                "interface": "internal",
+                "region": "RegionOne",
+                "url": "http://localhost:5000"
+            },
+            {
+                "id": "c609fc430175452290b62a4242e8a7e8",
+                "interface": "admin",
+                "region": "RegionTwo",
+                "url": "http://localhost:35357"
+            }
+        ],
+        "id": "4363ae44bdf34a3981fde3b823cb9aa2",
+        "type": "identity",
+        "name": "keystone"
+    }
+]"#;
+
+    fn token(sample: &str) -> Token {
+        serde_json::from_str::<TokenRoot>(sample).unwrap().token
+    }
+
+    #[test]
+    fn test_validation_query() {
+        let query = TokenValidationQuery::default();
+        assert_eq!(serde_json::to_value(query).unwrap(), serde_json::json!({}));
+        let query = TokenValidationQuery {
+            nocatalog: true,
+            allow_expired: true,
+        };
+        assert_eq!(
+            serde_json::to_value(query).unwrap(),
+            serde_json::json!({"nocatalog": true, "allow_expired": true})
+        );
+    }
+
+    #[test]
+    fn test_headers_user() {
+        let token = token(TOKEN);
+        let headers = AuthHeaders::new(Some(&token)).to_headers();
+        let expected = vec![
+            ("X-Identity-Status", "Confirmed"),
+            ("X-User-Id", "ee4dfb6e5540447cb3741905149d9b6e"),
+            ("X-User-Name", "admin"),
+            ("X-User-Domain-Id", "default"),
+            ("X-User-Domain-Name", "Default"),
+            ("X-Project-Id", "a6944d763bf64ee6a275f1263fae0352"),
+            ("X-Project-Name", "admin"),
+            ("X-Project-Domain-Id", "default"),
+            ("X-Project-Domain-Name", "Default"),
+            ("X-Roles", "admin,member"),
+            ("X-Is-Admin-Project", "True"),
+        ];
+        let headers_ref = headers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(headers_ref, expected);
+    }
+
+    #[test]
+    fn test_headers_catalog() {
+        let mut token = token(TOKEN);
+        token.catalog = serde_json::from_str(CATALOG).unwrap();
+        let value = AuthHeaders::new(Some(&token));
+        let headers = value.to_headers();
+        let catalog = headers
+            .iter()
+            .find(|(k, _)| k == SERVICE_CATALOG_HEADER)
+            .map(|(_, v)| serde_json::from_str::<Value>(v).unwrap())
+            .unwrap();
+        assert_eq!(
+            catalog,
+            serde_json::json!([{
+                "type": "identity",
+                "name": "keystone",
+                "endpoints": [
+                    {
+                        "region": "RegionOne",
+                        "publicURL": "http://localhost:5000",
+                        "internalURL": "http://localhost:5000"
+                    },
+                    {
+                        "region": "RegionTwo",
+                        "adminURL": "http://localhost:35357"
+                    }
+                ]
+            }])
+        );
+
+        let parsed =
+            AuthHeaders::from_headers(headers.iter().map(|(k, v)| (k, v.as_str()))).unwrap();
+        assert_eq!(parsed, value);
+        assert!(AuthHeaders::new(None)
+            .with_service(Some(&token))
+            .service_catalog
+            .is_none());
+    }
+
+    #[test]
+    fn test_headers_round_trip() {
+        let user = token(TOKEN);
+        let service = token(SERVICE_TOKEN);
+        for value in &[
+            AuthHeaders::new(Some(&user)),
+            AuthHeaders::new(None),
+            AuthHeaders::new(Some(&user)).with_service(Some(&service)),
+            AuthHeaders::new(None).with_service(None),
+            AuthHeaders::new(Some(&service)),
+        ] {
+            let headers = value.to_headers();
+            let parsed =
+                AuthHeaders::from_headers(headers.iter().map(|(k, v)| (k, v.as_str()))).unwrap();
+            assert_eq!(&parsed, value);
+        }
+    }
+
+    #[test]
+    fn test_headers_service() {
+        let user = token(TOKEN);
+        let service = token(SERVICE_TOKEN);
+        let headers = AuthHeaders::new(Some(&user))
+            .with_service(Some(&service))
+            .to_headers();
+        let find = |name: &str| {
+            headers
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(find("X-Service-Identity-Status"), Some("Confirmed"));
+        assert_eq!(find("X-Service-User-Name"), Some("nova"));
+        assert_eq!(find("X-Service-Roles"), Some("service"));
+        assert_eq!(find("X-Service-Project-Id"), None);
+        assert_eq!(find("X-System-Scope"), None);
+
+        let headers = AuthHeaders::new(Some(&service)).to_headers();
+        assert!(headers.contains(&("X-System-Scope".to_string(), "all".to_string())));
+        assert!(headers.contains(&("X-Is-Admin-Project".to_string(), "False".to_string())));
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let parsed = AuthHeaders::from_headers(vec![
+            ("x-identity-status", "Confirmed"),
+            ("x-user-id", "u1"),
+            ("x-user-name", "alice"),
+            ("x-user-domain-id", "default"),
+            ("x-user-domain-name", "Default"),
+            ("x-domain-id", "d1"),
+            ("x-domain-name", "Engineering"),
+            ("x-roles", "reader, member"),
+        ])
+        .unwrap();
+        let user = parsed.user.unwrap();
+        assert_eq!(user.user_name, "alice");
+        assert_eq!(user.domain_name.unwrap(), "Engineering");
+        assert!(user.project_id.is_none());
+        assert_eq!(user.roles, vec!["reader", "member"]);
+        assert!(user.is_admin_project);
+        assert!(parsed.service_status.is_none());
+    }
+
+    #[test]
+    fn test_from_headers_errors() {
+        assert_eq!(
+            AuthHeaders::from_headers(Vec::<(&str, &str)>::new()),
+            Err(HeaderError::Missing("X-Identity-Status".to_string()))
+        );
+        assert_eq!(
+            AuthHeaders::from_headers(vec![("X-Identity-Status", "Yes")]),
+            Err(HeaderError::InvalidValue {
+                header: "X-Identity-Status".to_string(),
+                value: "Yes".to_string()
+            })
+        );
+        assert_eq!(
+            AuthHeaders::from_headers(vec![("X-Identity-Status", "Confirmed")]),
+            Err(HeaderError::Missing("X-User-Id".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_is_auth_header() {
+        for name in &[
+            "X-Identity-Status",
+            "x-roles",
+            "X-SERVICE-USER-ID",
+            "X-Tenant-Id",
+            "x-service-catalog",
+        ] {
+            assert!(is_auth_header(name), "{}", name);
+        }
+        for name in &["X-Auth-Token", "X-Service-Token", "Content-Type"] {
+            assert!(!is_auth_header(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! OS-TRUST JSON structures and protocol bits.
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use super::super::common::{self, IdAndName, ResourceLinks};
+use super::IdOrName;
+
+/// A request to create a trust.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TrustCreate {
+    pub trustor_user_id: String,
+    pub trustee_user_id: String,
+    /// Whether the trustee acts as the trustor in issued tokens.
+    pub impersonation: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub roles: Vec<IdOrName>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
+    /// Number of tokens that can be issued with the trust (unlimited if `None`).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub remaining_uses: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub allow_redelegation: Option<bool>,
+    /// Maximum depth of the redelegation chain.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub redelegation_count: Option<u32>,
+}
+
+/// A trust creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TrustCreateRoot {
+    pub trust: TrustCreate,
+}
+
+/// A trust.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Trust {
+    pub id: String,
+    pub trustor_user_id: String,
+    pub trustee_user_id: String,
+    pub impersonation: bool,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<IdAndName>,
+    #[serde(deserialize_with = "common::opt_datetime_as_utc", default)]
+    pub expires_at: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub remaining_uses: Option<u32>,
+    #[serde(default)]
+    pub allow_redelegation: bool,
+    #[serde(default)]
+    pub redelegation_count: Option<u32>,
+    /// The trust this one was redelegated from.
+    #[serde(default)]
+    pub redelegated_trust_id: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A trust response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TrustRoot {
+    pub trust: Trust,
+}
+
+/// A list of trusts.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TrustsRoot {
+    pub trusts: Vec<Trust>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Query filters for listing trusts.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TrustFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trustor_user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trustee_user_id: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use super::super::super::common::test;
+    use super::super::IdOrName;
+    use super::*;
+
+    const TRUST_CREATE: &str = r#"
+{
+    "trust": {
+        "expires_at": "2013-02-27T18:30:59.999999Z",
+        "impersonation": true,
+        "allow_redelegation": true,
+        "project_id": "ddef321",
+        "roles": [
+            {
+                "name": "member"
+            }
+        ],
+        "trustee_user_id": "86c0d5",
+        "trustor_user_id": "a0fdfd"
+    }
+}"#;
+
+    const TRUST: &str = r#"
+{
+    "trust": {
+        "expires_at": "2013-02-27T18:30:59.999999Z",
+        "id": "1ff900",
+        "impersonation": true,
+        "redelegation_count": 10,
+        "allow_redelegation": true,
+        "links": {
+            "self": "http://example.com/identity/v3/OS-TRUST/trusts/1ff900"
+        },
+        "project_id": "0f1233",
+        "remaining_uses": null,
+        "roles": [
+            {
+                "id": "ed7b78",
+                "links": {
+                    "self": "http://example.com/identity/v3/roles/ed7b78"
+                },
+                "name": "member"
+            }
+        ],
+        "roles_links": {
+            "next": null,
+            "previous": null,
+            "self": "http://example.com/identity/v3/OS-TRUST/trusts/1ff900/roles"
+        },
+        "trustee_user_id": "86c0d5",
+        "trustor_user_id": "a0fdfd"
+    }
+}"#;
+
+    const TRUSTS: &str = r#"
+{
+    "trusts": [
+        {
+            "id": "1ff900",
+            "expires_at": null,
+            "impersonation": false,
+            "links": {
+                "self": "http://example.com/identity/v3/OS-TRUST/trusts/1ff900"
+            },
+            "project_id": "0f1233",
+            "remaining_uses": 3,
+            "trustee_user_id": "86c0d5",
+            "trustor_user_id": "a0fdfd",
+            "redelegated_trust_id": "4a3b2c"
+        }
+    ],
+    "links": {
+        "self": "http://example.com/identity/v3/OS-TRUST/trusts",
+        "previous": null,
+        "next": null
+    }
+}"#;
+
+    #[test]
+    fn test_trust_create() {
+        let value = TrustCreateRoot {
+            trust: TrustCreate {
+                trustor_user_id: "a0fdfd".to_string(),
+                trustee_user_id: "86c0d5".to_string(),
+                impersonation: true,
+                project_id: Some("ddef321".to_string()),
+                roles: vec![IdOrName::from_name("member")],
+                expires_at: Some(
+                    DateTime::parse_from_rfc3339("2013-02-27T18:30:59.999999Z").unwrap(),
+                ),
+                remaining_uses: None,
+                allow_redelegation: Some(true),
+                redelegation_count: None,
+            },
+        };
+        test::compare(TRUST_CREATE, value);
+        test::round_trip::<TrustCreateRoot>(TRUST_CREATE);
+    }
+
+    #[test]
+    fn test_trust() {
+        let root: TrustRoot = serde_json::from_str(TRUST).unwrap();
+        let trust = root.trust;
+        assert_eq!(trust.id, "1ff900");
+        assert!(trust.impersonation);
+        assert!(trust.allow_redelegation);
+        assert_eq!(trust.redelegation_count, Some(10));
+        assert!(trust.remaining_uses.is_none());
+        assert!(trust.expires_at.is_some());
+        assert_eq!(trust.roles[0].name, "member");
+        assert!(trust.links.self_link.is_some());
+    }
+
+    #[test]
+    fn test_trusts() {
+        let root: TrustsRoot = serde_json::from_str(TRUSTS).unwrap();
+        assert_eq!(root.trusts.len(), 1);
+        let trust = &root.trusts[0];
+        assert!(trust.expires_at.is_none());
+        assert!(trust.roles.is_empty());
+        assert_eq!(trust.remaining_uses, Some(3));
+        assert_eq!(trust.redelegated_trust_id.as_ref().unwrap(), "4a3b2c");
+        assert!(root.links.next.is_none());
+    }
+
+    #[test]
+    fn test_trust_filter() {
+        let filter = TrustFilter {
+            trustee_user_id: Some("86c0d5".to_string()),
+            ..TrustFilter::default()
+        };
+        test::compare(r#"{"trustee_user_id": "86c0d5"}"#, filter);
+    }
+}
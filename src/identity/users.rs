@@ -0,0 +1,574 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Users and groups JSON structures and protocol bits.
+
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use super::super::common::{self, ResourceLinks};
+use super::{IdOrName, REDACTED};
+
+/// User options.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UserOptions {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ignore_change_password_upon_first_use: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ignore_password_expiry: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ignore_lockout_failure_attempts: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ignore_user_inactivity: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub lock_password: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub multi_factor_auth_enabled: Option<bool>,
+    /// Lists of authentication methods, one of which must be fully satisfied.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub multi_factor_auth_rules: Option<Vec<Vec<String>>>,
+}
+
+/// A request to create a user.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct UserCreate {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default_project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub options: Option<UserOptions>,
+}
+
+/// A user creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UserCreateRoot {
+    pub user: UserCreate,
+}
+
+/// A request to update a user.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct UserUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default_project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub password: Option<String>,
+    /// Options to change, unset options are left intact.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub options: Option<UserOptions>,
+}
+
+/// A user update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UserUpdateRoot {
+    pub user: UserUpdate,
+}
+
+/// A request by a user to change their own password.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PasswordChange {
+    pub original_password: String,
+    pub password: String,
+}
+
+/// A password change request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PasswordChangeRoot {
+    pub user: PasswordChange,
+}
+
+/// A user.
+#[derive(Clone, Debug, Deserialize)]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    pub domain_id: String,
+    #[serde(default)]
+    pub default_project_id: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "super::default_enabled")]
+    pub enabled: bool,
+    #[serde(deserialize_with = "common::opt_datetime_as_utc", default)]
+    pub password_expires_at: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub options: UserOptions,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A user response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UserRoot {
+    pub user: User,
+}
+
+/// A list of users.
+///
+/// Also returned when listing members of a group.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UsersRoot {
+    pub users: Vec<User>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Query filters for listing users.
+///
+/// The `password_expires_at` filter has the form `{operator}:{timestamp}`, e.g.
+/// `lt:2016-12-08T22:02:00Z`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct UserFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idp_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_expires_at: Option<String>,
+}
+
+/// A request to create a group.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GroupCreate {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A group creation request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GroupCreateRoot {
+    pub group: GroupCreate,
+}
+
+/// A request to update a group.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct GroupUpdate {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+/// A group update request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GroupUpdateRoot {
+    pub group: GroupUpdate,
+}
+
+/// A group.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Group {
+    pub id: String,
+    pub name: String,
+    pub domain_id: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// A group response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GroupRoot {
+    pub group: Group,
+}
+
+/// A list of groups.
+///
+/// Also returned when listing groups of a user.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GroupsRoot {
+    pub groups: Vec<Group>,
+    #[serde(default)]
+    pub links: ResourceLinks,
+}
+
+/// Query filters for listing groups.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GroupFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Query filters for listing members of a group.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GroupMemberFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_expires_at: Option<String>,
+}
+
+fn redacted_password(password: &Option<String>) -> Option<&'static str> {
+    password.as_ref().map(|_| REDACTED)
+}
+
+impl UserCreate {
+    /// A copy with the password replaced by a placeholder.
+    pub fn redacted(&self) -> UserCreate {
+        UserCreate {
+            password: redacted_password(&self.password).map(From::from),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for UserCreate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserCreate")
+            .field("name", &self.name)
+            .field("domain_id", &self.domain_id)
+            .field("default_project_id", &self.default_project_id)
+            .field("description", &self.description)
+            .field("enabled", &self.enabled)
+            .field("password", &redacted_password(&self.password))
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl UserUpdate {
+    /// A copy with the password replaced by a placeholder.
+    pub fn redacted(&self) -> UserUpdate {
+        UserUpdate {
+            password: redacted_password(&self.password).map(From::from),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Debug for UserUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserUpdate")
+            .field("name", &self.name)
+            .field("domain_id", &self.domain_id)
+            .field("default_project_id", &self.default_project_id)
+            .field("description", &self.description)
+            .field("enabled", &self.enabled)
+            .field("password", &redacted_password(&self.password))
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl fmt::Debug for PasswordChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PasswordChange")
+            .field("original_password", &REDACTED)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl User {
+    /// Whether the user matches the reference.
+    pub fn matches(&self, reference: &IdOrName) -> bool {
+        match reference {
+            IdOrName::Id(ref id) => self.id == *id,
+            IdOrName::Name(ref name) => self.name == *name,
+        }
+    }
+}
+
+impl UsersRoot {
+    /// Find a user by its ID or name.
+    pub fn find(&self, reference: &IdOrName) -> Option<&User> {
+        self.users.iter().find(|user| user.matches(reference))
+    }
+}
+
+impl Group {
+    /// Whether the group matches the reference.
+    pub fn matches(&self, reference: &IdOrName) -> bool {
+        match reference {
+            IdOrName::Id(ref id) => self.id == *id,
+            IdOrName::Name(ref name) => self.name == *name,
+        }
+    }
+}
+
+impl GroupsRoot {
+    /// Find a group by its ID or name.
+    pub fn find(&self, reference: &IdOrName) -> Option<&Group> {
+        self.groups.iter().find(|group| group.matches(reference))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::super::IdOrName;
+    use super::*;
+
+    const USER_CREATE: &str = r#"
+{
+    "user": {
+        "default_project_id": "263fd9",
+        "domain_id": "1789d1",
+        "enabled": true,
+        "name": "James Doe",
+        "password": "secretsecret",
+        "description": "James Doe user",
+        "options": {
+            "ignore_password_expiry": true,
+            "multi_factor_auth_enabled": true,
+            "multi_factor_auth_rules": [
+                ["password", "totp"],
+                ["password", "custom-auth-method"]
+            ]
+        }
+    }
+}"#;
+
+    const USER: &str = r#"
+{
+    "user": {
+        "default_project_id": "263fd9",
+        "description": "James Doe user",
+        "domain_id": "1789d1",
+        "email": "jdoe@example.com",
+        "enabled": true,
+        "id": "ff4e51",
+        "links": {
+            "self": "https://example.com/identity/v3/users/ff4e51"
+        },
+        "name": "James Doe",
+        "options": {
+            "ignore_password_expiry": true
+        },
+        "password_expires_at": "2016-11-06T15:32:17.000000"
+    }
+}"#;
+
+    const USERS: &str = r#"
+{
+    "links": {
+        "next": null,
+        "previous": null,
+        "self": "http://example.com/identity/v3/groups/9ce0ad/users"
+    },
+    "users": [
+        {
+            "domain_id": "default",
+            "id": "2844b2",
+            "links": {
+                "self": "http://example.com/identity/v3/users/2844b2"
+            },
+            "name": "admin",
+            "options": {},
+            "password_expires_at": null
+        },
+        {
+            "domain_id": "default",
+            "enabled": false,
+            "id": "3ff9f9",
+            "name": "demo",
+            "options": {
+                "lock_password": true
+            },
+            "password_expires_at": "2016-11-06T15:32:17.000000Z"
+        }
+    ]
+}"#;
+
+    const GROUPS: &str = r#"
+{
+    "groups": [
+        {
+            "description": "Developers cleared for work on all general projects",
+            "domain_id": "1789d1",
+            "id": "ea167b",
+            "links": {
+                "self": "https://example.com/identity/v3/groups/ea167b"
+            },
+            "name": "Developers"
+        },
+        {
+            "description": "",
+            "domain_id": "1789d1",
+            "id": "a62db1",
+            "name": "Secure Developers"
+        }
+    ],
+    "links": {
+        "self": "http://example.com/identity/v3/users/9fe1d3/groups",
+        "previous": null,
+        "next": null
+    }
+}"#;
+
+    #[test]
+    fn test_user_create() {
+        let value = UserCreateRoot {
+            user: UserCreate {
+                name: "James Doe".to_string(),
+                domain_id: Some("1789d1".to_string()),
+                default_project_id: Some("263fd9".to_string()),
+                description: Some("James Doe user".to_string()),
+                enabled: Some(true),
+                password: Some("secretsecret".to_string()),
+                options: Some(UserOptions {
+                    ignore_password_expiry: Some(true),
+                    multi_factor_auth_enabled: Some(true),
+                    multi_factor_auth_rules: Some(vec![
+                        vec!["password".to_string(), "totp".to_string()],
+                        vec!["password".to_string(), "custom-auth-method".to_string()],
+                    ]),
+                    ..UserOptions::default()
+                }),
+            },
+        };
+        let debug = format!("{:?}", value);
+        assert!(!debug.contains("secretsecret"));
+        assert_eq!(value.user.redacted().password.unwrap(), REDACTED);
+        test::compare(USER_CREATE, value);
+        test::round_trip::<UserCreateRoot>(USER_CREATE);
+    }
+
+    #[test]
+    fn test_user_update() {
+        let value = UserUpdateRoot {
+            user: UserUpdate {
+                enabled: Some(false),
+                options: Some(UserOptions {
+                    ignore_lockout_failure_attempts: Some(true),
+                    ..UserOptions::default()
+                }),
+                ..UserUpdate::default()
+            },
+        };
+        test::compare(
+            r#"{"user": {"enabled": false, "options": {"ignore_lockout_failure_attempts": true}}}"#,
+            value,
+        );
+    }
+
+    #[test]
+    fn test_password_change() {
+        let value = PasswordChangeRoot {
+            user: PasswordChange {
+                original_password: "secretsecret".to_string(),
+                password: "newsecretsecret".to_string(),
+            },
+        };
+        assert!(!format!("{:?}", value).contains("secret\""));
+        test::compare(
+            r#"{"user": {"original_password": "secretsecret", "password": "newsecretsecret"}}"#,
+            value,
+        );
+    }
+
+    #[test]
+    fn test_user() {
+        let user = serde_json::from_str::<UserRoot>(USER).unwrap().user;
+        assert_eq!(user.id, "ff4e51");
+        assert!(user.enabled);
+        assert_eq!(user.options.ignore_password_expiry, Some(true));
+        assert!(user.options.multi_factor_auth_rules.is_none());
+        assert_eq!(
+            user.password_expires_at.unwrap().to_rfc3339(),
+            "2016-11-06T15:32:17+00:00"
+        );
+        assert!(user.links.self_link.is_some());
+    }
+
+    #[test]
+    fn test_users() {
+        let root: UsersRoot = serde_json::from_str(USERS).unwrap();
+        assert_eq!(root.users.len(), 2);
+        assert!(root.users[0].enabled);
+        assert!(root.users[0].password_expires_at.is_none());
+        assert!(!root.users[1].enabled);
+        assert_eq!(root.users[1].options.lock_password, Some(true));
+        assert_eq!(
+            root.find(&IdOrName::from_name("demo")).unwrap().id,
+            "3ff9f9"
+        );
+        assert_eq!(
+            root.find(&IdOrName::from_id("2844b2")).unwrap().name,
+            "admin"
+        );
+        assert!(root.find(&IdOrName::from_id("demo")).is_none());
+    }
+
+    #[test]
+    fn test_user_filter() {
+        let filter = UserFilter {
+            domain_id: Some("default".to_string()),
+            password_expires_at: Some("lt:2016-12-08T22:02:00Z".to_string()),
+            ..UserFilter::default()
+        };
+        test::compare(
+            r#"{"domain_id": "default", "password_expires_at": "lt:2016-12-08T22:02:00Z"}"#,
+            filter,
+        );
+    }
+
+    #[test]
+    fn test_group_create() {
+        let value = GroupCreateRoot {
+            group: GroupCreate {
+                name: "Contract developers".to_string(),
+                domain_id: Some("default".to_string()),
+                description: Some("Contract developers for the project".to_string()),
+            },
+        };
+        test::compare(
+            r#"{"group": {"name": "Contract developers", "domain_id": "default",
+                "description": "Contract developers for the project"}}"#,
+            value,
+        );
+    }
+
+    #[test]
+    fn test_groups() {
+        let root: GroupsRoot = serde_json::from_str(GROUPS).unwrap();
+        assert_eq!(root.groups.len(), 2);
+        assert_eq!(root.groups[1].description.as_ref().unwrap(), "");
+        assert!(root.groups[1].links.self_link.is_none());
+        assert_eq!(
+            root.find(&IdOrName::from_name("Developers")).unwrap().id,
+            "ea167b"
+        );
+        assert!(root.links.next.is_none());
+    }
+}
@@ -0,0 +1,542 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Legacy Identity V2.0 JSON structures.
+//!
+//! Responses can be converted into V3 `Token` and `CatalogRecord` structures. Identity V2.0 has
+//! no domains, all users and projects are assumed to belong to the default domain.
+
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use super::super::common::{self, IdAndName, IdRef};
+use super::{CatalogRecord, Interface, TokenProject, TokenUser, REDACTED};
+
+/// ID of the default domain.
+pub const DEFAULT_DOMAIN_ID: &str = "default";
+
+/// Name of the default domain.
+pub const DEFAULT_DOMAIN_NAME: &str = "Default";
+
+/// A user name and a password.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct PasswordCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Credentials for authentication.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum Credentials {
+    /// Authentication with a user name and a password.
+    #[serde(rename = "passwordCredentials")]
+    Password(PasswordCredentials),
+    /// Authentication with an existing token.
+    #[serde(rename = "token")]
+    Token(IdRef),
+}
+
+/// An authentication request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Auth {
+    #[serde(flatten)]
+    pub credentials: Credentials,
+    #[serde(rename = "tenantId", skip_serializing_if = "Option::is_none", default)]
+    pub tenant_id: Option<String>,
+    #[serde(
+        rename = "tenantName",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub tenant_name: Option<String>,
+}
+
+/// An authentication request root.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuthRoot {
+    pub auth: Auth,
+}
+
+/// A tenant (project) in a token.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "super::default_enabled")]
+    pub enabled: bool,
+}
+
+/// A token in the access response.
+#[derive(Clone, Deserialize)]
+pub struct AccessToken {
+    pub id: String,
+    #[serde(deserialize_with = "common::datetime_as_utc")]
+    pub issued_at: DateTime<FixedOffset>,
+    #[serde(deserialize_with = "common::datetime_as_utc")]
+    pub expires: DateTime<FixedOffset>,
+    #[serde(default)]
+    pub tenant: Option<Tenant>,
+    #[serde(default)]
+    pub audit_ids: Vec<String>,
+}
+
+/// An endpoint in the service catalog.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Endpoint {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub region: String,
+    #[serde(rename = "publicURL", default)]
+    pub public_url: Option<String>,
+    #[serde(rename = "internalURL", default)]
+    pub internal_url: Option<String>,
+    #[serde(rename = "adminURL", default)]
+    pub admin_url: Option<String>,
+}
+
+/// A service catalog record.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ServiceCatalogRecord {
+    #[serde(rename = "type")]
+    pub service_type: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub endpoints: Vec<Endpoint>,
+}
+
+/// A role of the user.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessRole {
+    /// Role ID (not always returned).
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+}
+
+/// A user in the access response.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessUser {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<AccessRole>,
+}
+
+/// Access metadata.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AccessMetadata {
+    /// Role IDs.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// An access response.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Access {
+    pub token: AccessToken,
+    #[serde(rename = "serviceCatalog", default)]
+    pub service_catalog: Vec<ServiceCatalogRecord>,
+    pub user: AccessUser,
+    #[serde(default)]
+    pub metadata: AccessMetadata,
+}
+
+/// An access response root.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AccessRoot {
+    pub access: Access,
+}
+
+impl Auth {
+    /// Authentication with a user name and a password.
+    pub fn password<S1, S2>(username: S1, password: S2) -> Auth
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Auth {
+            credentials: Credentials::Password(PasswordCredentials {
+                username: username.into(),
+                password: password.into(),
+            }),
+            tenant_id: None,
+            tenant_name: None,
+        }
+    }
+
+    /// Authentication with an existing token.
+    pub fn token<S: Into<String>>(token: S) -> Auth {
+        Auth {
+            credentials: Credentials::Token(IdRef { id: token.into() }),
+            tenant_id: None,
+            tenant_name: None,
+        }
+    }
+
+    /// A copy with all secrets replaced by a placeholder.
+    pub fn redacted(&self) -> Auth {
+        Auth {
+            credentials: self.credentials.redacted(),
+            ..self.clone()
+        }
+    }
+}
+
+impl AuthRoot {
+    /// A copy with all secrets replaced by a placeholder.
+    #[inline]
+    pub fn redacted(&self) -> AuthRoot {
+        AuthRoot {
+            auth: self.auth.redacted(),
+        }
+    }
+}
+
+impl Credentials {
+    /// A copy with all secrets replaced by a placeholder.
+    pub fn redacted(&self) -> Credentials {
+        match *self {
+            Credentials::Password(ref creds) => Credentials::Password(PasswordCredentials {
+                username: creds.username.clone(),
+                password: REDACTED.to_string(),
+            }),
+            Credentials::Token(..) => Credentials::Token(IdRef {
+                id: REDACTED.to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for PasswordCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PasswordCredentials")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Credentials::Password(ref creds) => f.debug_tuple("Password").field(creds).finish(),
+            Credentials::Token(..) => f.debug_tuple("Token").field(&REDACTED).finish(),
+        }
+    }
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("id", &REDACTED)
+            .field("issued_at", &self.issued_at)
+            .field("expires", &self.expires)
+            .field("tenant", &self.tenant)
+            .field("audit_ids", &self.audit_ids)
+            .finish()
+    }
+}
+
+impl Endpoint {
+    /// URL for the given interface (if any).
+    pub fn url(&self, interface: &Interface) -> Option<&str> {
+        match *interface {
+            Interface::Public => self.public_url.as_ref(),
+            Interface::Internal => self.internal_url.as_ref(),
+            Interface::Admin => self.admin_url.as_ref(),
+            Interface::Other(..) => None,
+        }
+        .map(String::as_str)
+    }
+
+    /// Convert into V3 endpoints, one per interface.
+    pub fn to_v3(&self) -> Vec<super::Endpoint> {
+        [Interface::Public, Interface::Internal, Interface::Admin]
+            .iter()
+            .filter_map(|interface| {
+                self.url(interface).map(|url| super::Endpoint {
+                    id: self.id.clone(),
+                    interface: interface.clone(),
+                    region: self.region.clone(),
+                    region_id: Some(self.region.clone()),
+                    url: url.to_string(),
+                    enabled: true,
+                })
+            })
+            .collect()
+    }
+}
+
+impl From<ServiceCatalogRecord> for CatalogRecord {
+    fn from(value: ServiceCatalogRecord) -> CatalogRecord {
+        CatalogRecord {
+            id: None,
+            service_type: value.service_type,
+            name: value.name,
+            endpoints: value.endpoints.iter().flat_map(Endpoint::to_v3).collect(),
+        }
+    }
+}
+
+impl From<Access> for super::Token {
+    /// Convert into a V3 token.
+    ///
+    /// The token ID is not a part of V3 token structures and must be taken from
+    /// `access.token.id` before the conversion. Roles without IDs use names instead.
+    fn from(value: Access) -> super::Token {
+        let project = value.token.tenant.map(|tenant| TokenProject {
+            id: tenant.id,
+            name: tenant.name,
+            domain: default_domain(),
+        });
+        super::Token {
+            roles: value
+                .user
+                .roles
+                .into_iter()
+                .map(|role| {
+                    let name = role.name;
+                    IdAndName {
+                        id: role.id.unwrap_or_else(|| name.clone()),
+                        name,
+                    }
+                })
+                .collect(),
+            expires_at: value.token.expires,
+            issued_at: value.token.issued_at,
+            catalog: value
+                .service_catalog
+                .into_iter()
+                .map(CatalogRecord::from)
+                .collect(),
+            user: TokenUser {
+                id: value.user.id,
+                name: value.user.name,
+                domain: default_domain(),
+                password_expires_at: None,
+                federation: None,
+            },
+            methods: Vec::new(),
+            audit_ids: value.token.audit_ids,
+            project,
+            is_domain: false,
+            domain: None,
+            system: None,
+            application_credential: None,
+            trust: None,
+            is_admin_project: None,
+        }
+    }
+}
+
+fn default_domain() -> IdAndName {
+    IdAndName {
+        id: DEFAULT_DOMAIN_ID.to_string(),
+        name: DEFAULT_DOMAIN_NAME.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::common::test;
+    use super::super::{Token, TokenScope};
+    use super::*;
+
+    const PASSWORD_TENANT_NAME: &str = r#"
+{
+    "auth": {
+        "passwordCredentials": {
+            "username": "demo",
+            "password": "secrete"
+        },
+        "tenantName": "demo"
+    }
+}"#;
+
+    const TOKEN_TENANT_ID: &str = r#"
+{
+    "auth": {
+        "token": {
+            "id": "abcdef"
+        },
+        "tenantId": "c1a7eba8e1bd4ea1b5b0e4e9d2a6f1e3"
+    }
+}"#;
+
+    const ACCESS: &str = r#"
+{
+    "access": {
+        "token": {
+            "issued_at": "2014-01-30T15:30:58.819584",
+            "expires": "2014-01-31T15:30:58Z",
+            "id": "aaaaa-bbbbb-ccccc-dddd",
+            "tenant": {
+                "description": null,
+                "enabled": true,
+                "id": "fc394f2ab2df4114bde39905f800dc57",
+                "name": "demo"
+            },
+            "audit_ids": [
+                "8c3s8mBaS0CnWbAeGbvYyw"
+            ]
+        },
+        "serviceCatalog": [
+            {
+                "endpoints": [
+                    {
+                        "adminURL": "http://23.253.72.207:8774/v2/fc394f2ab2df4114bde39905f800dc57",
+                        "region": "RegionOne",
+                        "internalURL": "http://23.253.72.207:8774/v2/fc394f2ab2df4114bde39905f800dc57",
+                        "id": "2dad48f09e2a447a9bf852bcd93548ef",
+                        "publicURL": "http://23.253.72.207:8774/v2/fc394f2ab2df4114bde39905f800dc57"
+                    }
+                ],
+                "endpoints_links": [],
+                "type": "compute",
+                "name": "nova"
+            },
+            {
+                "endpoints": [
+                    {
+                        "region": "RegionOne",
+                        "publicURL": "http://23.253.72.207:5000/v2.0"
+                    }
+                ],
+                "endpoints_links": [],
+                "type": "identity",
+                "name": "keystone"
+            }
+        ],
+        "user": {
+            "username": "demo",
+            "roles_links": [],
+            "id": "9a6590b2ab024747bc2167c4e064d00d",
+            "roles": [
+                {
+                    "name": "Member"
+                },
+                {
+                    "name": "anotherrole"
+                }
+            ],
+            "name": "demo"
+        },
+        "metadata": {
+            "is_admin": 0,
+            "roles": [
+                "7598ac3c634d4c3da4b9126a5f67ca2b",
+                "f95c0ab82d6045d9805033ee1fbc80d4"
+            ]
+        }
+    }
+}"#;
+
+    #[test]
+    fn test_password_tenant_name() {
+        let value = AuthRoot {
+            auth: Auth {
+                tenant_name: Some("demo".to_string()),
+                ..Auth::password("demo", "secrete")
+            },
+        };
+        test::compare(PASSWORD_TENANT_NAME, value);
+    }
+
+    #[test]
+    fn test_token_tenant_id() {
+        let value = AuthRoot {
+            auth: Auth {
+                tenant_id: Some("c1a7eba8e1bd4ea1b5b0e4e9d2a6f1e3".to_string()),
+                ..Auth::token("abcdef")
+            },
+        };
+        test::compare(TOKEN_TENANT_ID, value);
+    }
+
+    #[test]
+    fn test_auth_round_trip() {
+        for sample in &[PASSWORD_TENANT_NAME, TOKEN_TENANT_ID] {
+            test::round_trip::<AuthRoot>(sample);
+        }
+    }
+
+    #[test]
+    fn test_auth_redacted() {
+        for sample in &[PASSWORD_TENANT_NAME, TOKEN_TENANT_ID] {
+            let value: AuthRoot = serde_json::from_str(sample).unwrap();
+            let debug = format!("{:?}", value);
+            assert!(!debug.contains("secrete"));
+            assert!(!debug.contains("abcdef"));
+            let json = serde_json::to_string(&value.redacted()).unwrap();
+            assert!(!json.contains("secrete"));
+            assert!(!json.contains("abcdef"));
+        }
+    }
+
+    #[test]
+    fn test_access() {
+        let root: AccessRoot = serde_json::from_str(ACCESS).unwrap();
+        let access = root.access;
+        assert_eq!(access.token.id, "aaaaa-bbbbb-ccccc-dddd");
+        assert!(!format!("{:?}", access.token).contains("aaaaa-bbbbb-ccccc-dddd"));
+        assert_eq!(access.token.issued_at.timestamp(), 1391095858);
+        assert_eq!(access.service_catalog.len(), 2);
+        let endpoint = &access.service_catalog[1].endpoints[0];
+        assert!(endpoint.id.is_none());
+        assert!(endpoint.admin_url.is_none());
+        assert_eq!(access.metadata.roles.len(), 2);
+    }
+
+    #[test]
+    fn test_access_to_v3() {
+        let root: AccessRoot = serde_json::from_str(ACCESS).unwrap();
+        let token = Token::from(root.access);
+        assert_eq!(token.user.name, "demo");
+        assert_eq!(token.user.domain.id, DEFAULT_DOMAIN_ID);
+        assert_eq!(token.audit_ids, vec!["8c3s8mBaS0CnWbAeGbvYyw"]);
+        match token.scope() {
+            TokenScope::Project(project) => {
+                assert_eq!(project.name, "demo");
+                assert_eq!(project.domain.name, DEFAULT_DOMAIN_NAME);
+            }
+            other => panic!("Unexpected scope {:?}", other),
+        }
+        let roles: Vec<_> = token.roles.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(roles, vec!["Member", "anotherrole"]);
+
+        assert_eq!(token.catalog.len(), 2);
+        let compute = &token.catalog[0];
+        assert_eq!(compute.service_type, "compute");
+        let interfaces: Vec<_> = compute
+            .endpoints
+            .iter()
+            .map(|e| e.interface.clone())
+            .collect();
+        assert_eq!(
+            interfaces,
+            vec![Interface::Public, Interface::Internal, Interface::Admin]
+        );
+        assert!(compute.endpoints.iter().all(|e| e.region == "RegionOne"));
+        let identity = &token.catalog[1];
+        assert_eq!(identity.endpoints.len(), 1);
+        assert_eq!(identity.endpoints[0].url, "http://23.253.72.207:5000/v2.0");
+    }
+}
@@ -41,7 +41,6 @@
 // versions of the compiler. Add more warnings here as you discover them.
 // Taken from https://github.com/rust-unofficial/patterns/
 #![deny(
-    const_err,
     dead_code,
     improper_ctypes,
     missing_copy_implementations,
@@ -51,7 +50,6 @@
     overflowing_literals,
     path_statements,
     patterns_in_fns_without_body,
-    private_in_public,
     trivial_casts,
     trivial_numeric_casts,
     unconditional_recursion,
@@ -69,4 +67,7 @@
 #![allow(missing_docs)]
 
 pub mod common;
+pub mod config;
 pub mod identity;
+pub mod policy;
+pub mod service_types;
@@ -0,0 +1,1022 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local evaluation of oslo.policy rules.
+//!
+//! Supports the rule language of oslo.policy (`role:`, `rule:` and generic checks, `@`, `!`,
+//! `and`, `or`, `not` and parentheses) and policy files in JSON or YAML. HTTP checks cannot be
+//! evaluated locally and always deny.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::identity::Token;
+
+/// Name of the rule used for unknown targets.
+pub const DEFAULT_RULE: &str = "default";
+
+/// A parsed policy rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// Always allow (`@` or an empty rule).
+    Always,
+    /// Always deny (`!`).
+    Never,
+    /// Check that the credentials have a role (`role:<name>`).
+    Role(String),
+    /// Reference to another rule (`rule:<name>`).
+    Reference(String),
+    /// Check a remote HTTP endpoint (`http:` or `https:`), always denies.
+    Http(String),
+    /// Compare a credential value or a literal with a value (`<key>:<value>`).
+    Generic { key: String, value: String },
+    /// Negation.
+    Not(Box<Rule>),
+    /// Conjunction.
+    And(Vec<Rule>),
+    /// Disjunction.
+    Or(Vec<Rule>),
+}
+
+/// A set of named rules.
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    pub rules: BTreeMap<String, Rule>,
+    /// Rule used for targets without a rule (`DEFAULT_RULE` by default).
+    pub default_rule: Option<String>,
+}
+
+/// Credentials to evaluate rules against.
+///
+/// Keys follow the policy values of oslo.context, nested values can be referenced with dotted
+/// paths.
+#[derive(Clone, Debug, Default)]
+pub struct Credentials {
+    values: Map<String, Value>,
+}
+
+/// A single evaluated check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Nesting level, starting with 0 for the evaluated rule.
+    pub depth: usize,
+    /// The check in the rule language.
+    pub check: String,
+    pub result: bool,
+}
+
+/// Result of evaluating a rule.
+#[derive(Clone, Debug)]
+pub struct Decision {
+    pub allowed: bool,
+    /// Evaluated checks in evaluation order (short-circuited checks are not included).
+    pub trace: Vec<TraceEntry>,
+}
+
+/// An error in a policy or during evaluation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyError {
+    /// The policy file cannot be parsed.
+    InvalidFormat(String),
+    /// A rule cannot be parsed.
+    InvalidRule {
+        /// Name of the rule if known.
+        name: Option<String>,
+        rule: String,
+        message: String,
+    },
+    /// The rule does not exist and there is no default rule.
+    RuleNotFound(String),
+    /// Rules reference each other recursively.
+    RecursiveRule(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RuleSource {
+    Text(String),
+    /// Legacy list-of-lists syntax: a disjunction of conjunctions.
+    Lists(Vec<Vec<String>>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Lexeme {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Check(String),
+}
+
+struct Parser<'a> {
+    tokens: Vec<Lexeme>,
+    position: usize,
+    source: &'a str,
+}
+
+struct Evaluator<'a> {
+    policy: &'a Policy,
+    credentials: &'a Credentials,
+    target: &'a Value,
+    trace: Vec<TraceEntry>,
+    stack: Vec<&'a str>,
+}
+
+impl Rule {
+    /// Whether the rule consists of a compound expression.
+    fn is_compound(&self) -> bool {
+        matches!(self, Rule::And(..) | Rule::Or(..))
+    }
+
+    fn parse_check(check: &str) -> Result<Rule, String> {
+        match check {
+            "@" => return Ok(Rule::Always),
+            "!" => return Ok(Rule::Never),
+            _ => {}
+        }
+        if (check.starts_with('"') || check.starts_with('\'')) && !check.contains(':') {
+            return Err(format!("unexpected string {}", check));
+        }
+        let mut parts = check.splitn(2, ':');
+        let kind = parts.next().unwrap_or_default();
+        let value = match parts.next() {
+            Some(value) if !kind.is_empty() => value.to_string(),
+            _ => return Err(format!("invalid check {}", check)),
+        };
+        Ok(match kind {
+            "role" => Rule::Role(value),
+            "rule" => Rule::Reference(value),
+            "http" | "https" => Rule::Http(check.to_string()),
+            _ => Rule::Generic {
+                key: kind.to_string(),
+                value,
+            },
+        })
+    }
+
+    fn from_source(name: &str, source: RuleSource) -> Result<Rule, PolicyError> {
+        let with_name = |err| match err {
+            PolicyError::InvalidRule { rule, message, .. } => PolicyError::InvalidRule {
+                name: Some(name.to_string()),
+                rule,
+                message,
+            },
+            other => other,
+        };
+        match source {
+            RuleSource::Text(text) => text.parse().map_err(with_name),
+            RuleSource::Lists(lists) => {
+                if lists.is_empty() {
+                    return Ok(Rule::Always);
+                }
+                let mut alternatives = Vec::with_capacity(lists.len());
+                for list in lists {
+                    let mut checks = Vec::with_capacity(list.len());
+                    for check in list {
+                        checks.push(Rule::parse_check(&check).map_err(|message| {
+                            PolicyError::InvalidRule {
+                                name: Some(name.to_string()),
+                                rule: check.clone(),
+                                message,
+                            }
+                        })?);
+                    }
+                    alternatives.push(collapse(checks, Rule::And));
+                }
+                Ok(collapse(alternatives, Rule::Or))
+            }
+        }
+    }
+}
+
+fn collapse(mut items: Vec<Rule>, build: fn(Vec<Rule>) -> Rule) -> Rule {
+    if items.len() == 1 {
+        items.remove(0)
+    } else {
+        build(items)
+    }
+}
+
+impl FromStr for Rule {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Rule, PolicyError> {
+        let tokens = tokenize(s);
+        if tokens.is_empty() {
+            return Ok(Rule::Always);
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            source: s,
+        };
+        let result = parser.parse_or()?;
+        if parser.position < parser.tokens.len() {
+            Err(parser.error("unexpected trailing tokens"))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn operand(f: &mut fmt::Formatter, rule: &Rule) -> fmt::Result {
+            if rule.is_compound() {
+                write!(f, "({})", rule)
+            } else {
+                write!(f, "{}", rule)
+            }
+        }
+
+        match self {
+            Rule::Always => f.write_str("@"),
+            Rule::Never => f.write_str("!"),
+            Rule::Role(ref name) => write!(f, "role:{}", name),
+            Rule::Reference(ref name) => write!(f, "rule:{}", name),
+            Rule::Http(ref check) => f.write_str(check),
+            Rule::Generic { ref key, ref value } => write!(f, "{}:{}", key, value),
+            Rule::Not(ref inner) => {
+                f.write_str("not ")?;
+                operand(f, inner)
+            }
+            Rule::And(ref items) | Rule::Or(ref items) => {
+                let separator = if let Rule::And(..) = self {
+                    " and "
+                } else {
+                    " or "
+                };
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(separator)?;
+                    }
+                    operand(f, item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn tokenize(rule: &str) -> Vec<Lexeme> {
+    let mut result = Vec::new();
+    for word in rule.split_whitespace() {
+        let trimmed = word.trim_start_matches('(');
+        for _ in 0..(word.len() - trimmed.len()) {
+            result.push(Lexeme::Open);
+        }
+        let check = trimmed.trim_end_matches(')');
+        match check.to_lowercase().as_str() {
+            "" => {}
+            "and" => result.push(Lexeme::And),
+            "or" => result.push(Lexeme::Or),
+            "not" => result.push(Lexeme::Not),
+            _ => result.push(Lexeme::Check(check.to_string())),
+        }
+        for _ in 0..(trimmed.len() - check.len()) {
+            result.push(Lexeme::Close);
+        }
+    }
+    result
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> PolicyError {
+        PolicyError::InvalidRule {
+            name: None,
+            rule: self.source.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Lexeme> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let result = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        result
+    }
+
+    fn parse_or(&mut self) -> Result<Rule, PolicyError> {
+        let mut items = vec![self.parse_and()?];
+        while self.peek() == Some(&Lexeme::Or) {
+            self.position += 1;
+            items.push(self.parse_and()?);
+        }
+        Ok(collapse(items, Rule::Or))
+    }
+
+    fn parse_and(&mut self) -> Result<Rule, PolicyError> {
+        let mut items = vec![self.parse_not()?];
+        while self.peek() == Some(&Lexeme::And) {
+            self.position += 1;
+            items.push(self.parse_not()?);
+        }
+        Ok(collapse(items, Rule::And))
+    }
+
+    fn parse_not(&mut self) -> Result<Rule, PolicyError> {
+        match self.next() {
+            Some(Lexeme::Not) => Ok(Rule::Not(Box::new(self.parse_not()?))),
+            Some(Lexeme::Open) => {
+                let result = self.parse_or()?;
+                match self.next() {
+                    Some(Lexeme::Close) => Ok(result),
+                    _ => Err(self.error("missing closing parenthesis")),
+                }
+            }
+            Some(Lexeme::Check(ref check)) => Rule::parse_check(check).map_err(|e| self.error(&e)),
+            Some(..) => Err(self.error("unexpected operator")),
+            None => Err(self.error("unexpected end of rule")),
+        }
+    }
+}
+
+impl Policy {
+    /// Parse a policy file in JSON or YAML.
+    pub fn parse(text: &str) -> Result<Policy, PolicyError> {
+        if text.trim_start().starts_with('{') {
+            Policy::from_json(text)
+        } else {
+            Policy::from_yaml(text)
+        }
+    }
+
+    /// Parse a policy file in JSON.
+    pub fn from_json(text: &str) -> Result<Policy, PolicyError> {
+        let sources: BTreeMap<String, RuleSource> = serde_json::from_str(text)
+            .map_err(|err| PolicyError::InvalidFormat(err.to_string()))?;
+        Policy::from_sources(sources)
+    }
+
+    /// Parse a policy file in YAML.
+    pub fn from_yaml(text: &str) -> Result<Policy, PolicyError> {
+        let sources: Option<BTreeMap<String, RuleSource>> = serde_yaml::from_str(text)
+            .map_err(|err| PolicyError::InvalidFormat(err.to_string()))?;
+        Policy::from_sources(sources.unwrap_or_default())
+    }
+
+    fn from_sources(sources: BTreeMap<String, RuleSource>) -> Result<Policy, PolicyError> {
+        let mut rules = BTreeMap::new();
+        for (name, source) in sources {
+            let rule = Rule::from_source(&name, source)?;
+            let _ = rules.insert(name, rule);
+        }
+        Ok(Policy {
+            rules,
+            default_rule: Some(DEFAULT_RULE.to_string()),
+        })
+    }
+
+    /// Override rules with rules from another policy (e.g. defaults with a policy file).
+    pub fn merge(&mut self, other: Policy) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Evaluate a named rule.
+    ///
+    /// Falls back to the default rule if the rule does not exist. Missing references inside
+    /// rules deny access like in oslo.policy.
+    pub fn enforce(
+        &self,
+        name: &str,
+        target: &Value,
+        credentials: &Credentials,
+    ) -> Result<Decision, PolicyError> {
+        let name = if self.rules.contains_key(name) {
+            name
+        } else {
+            match self.default_rule {
+                Some(ref default) if self.rules.contains_key(default) => default,
+                _ => return Err(PolicyError::RuleNotFound(name.to_string())),
+            }
+        };
+        self.check(&Rule::Reference(name.to_string()), target, credentials)
+    }
+
+    /// Evaluate an arbitrary rule using the rules of this policy for references.
+    pub fn check(
+        &self,
+        rule: &Rule,
+        target: &Value,
+        credentials: &Credentials,
+    ) -> Result<Decision, PolicyError> {
+        let mut evaluator = Evaluator {
+            policy: self,
+            credentials,
+            target,
+            trace: Vec::new(),
+            stack: Vec::new(),
+        };
+        let allowed = evaluator.evaluate(rule, 0)?;
+        Ok(Decision {
+            allowed,
+            trace: evaluator.trace,
+        })
+    }
+}
+
+impl Credentials {
+    /// Empty credentials.
+    pub fn new() -> Credentials {
+        Credentials::default()
+    }
+
+    /// Credentials of a token.
+    ///
+    /// Sets `user_id`, `user_domain_id`, `project_id`, `project_domain_id`, `domain_id`,
+    /// `system_scope`, `roles` (names), `trust_id` and `is_admin_project` (`true` unless the
+    /// token says otherwise).
+    pub fn from_token(token: &Token) -> Credentials {
+        let mut result = Credentials::new();
+        result.insert("user_id", token.user.id.clone());
+        result.insert("user_domain_id", token.user.domain.id.clone());
+        result.insert(
+            "project_id",
+            token.project.as_ref().map(|project| project.id.clone()),
+        );
+        result.insert(
+            "project_domain_id",
+            token
+                .project
+                .as_ref()
+                .map(|project| project.domain.id.clone()),
+        );
+        result.insert(
+            "domain_id",
+            token.domain.as_ref().map(|domain| domain.id.clone()),
+        );
+        result.insert(
+            "system_scope",
+            token
+                .system
+                .filter(|system| system.all)
+                .map(|_| "all".to_string()),
+        );
+        result.insert(
+            "roles",
+            token
+                .roles
+                .iter()
+                .map(|role| role.name.clone())
+                .collect::<Vec<_>>(),
+        );
+        result.insert(
+            "trust_id",
+            token.trust.as_ref().map(|trust| trust.id.clone()),
+        );
+        result.insert("is_admin_project", token.is_admin_project.unwrap_or(true));
+        result
+    }
+
+    /// Set a value.
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        let _ = self.values.insert(key.into(), value.into());
+    }
+
+    /// Get a value by its key or a dotted path.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        lookup(&self.values, path)
+    }
+
+    /// Role names.
+    pub fn roles(&self) -> Vec<&str> {
+        match self.values.get("roles") {
+            Some(Value::Array(ref roles)) => roles.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    fn evaluate(&mut self, rule: &'a Rule, depth: usize) -> Result<bool, PolicyError> {
+        let index = self.trace.len();
+        self.trace.push(TraceEntry {
+            depth,
+            check: rule.to_string(),
+            result: false,
+        });
+
+        let result = match rule {
+            Rule::Always => true,
+            Rule::Never | Rule::Http(..) => false,
+            Rule::Role(ref role) => match format_target(role, self.target) {
+                Some(role) => {
+                    let role = role.to_lowercase();
+                    self.credentials
+                        .roles()
+                        .iter()
+                        .any(|existing| existing.to_lowercase() == role)
+                }
+                None => false,
+            },
+            Rule::Generic { ref key, ref value } => self.generic(key, value),
+            Rule::Reference(ref name) => match self.policy.rules.get_key_value(name) {
+                Some((name, referenced)) => {
+                    if self.stack.contains(&name.as_str()) {
+                        let mut names = self
+                            .stack
+                            .iter()
+                            .map(|item| item.to_string())
+                            .collect::<Vec<_>>();
+                        names.push(name.clone());
+                        return Err(PolicyError::RecursiveRule(names));
+                    }
+                    self.stack.push(name);
+                    let result = self.evaluate(referenced, depth + 1)?;
+                    let _ = self.stack.pop();
+                    result
+                }
+                None => false,
+            },
+            Rule::Not(ref inner) => !self.evaluate(inner, depth + 1)?,
+            Rule::And(ref items) => {
+                let mut result = true;
+                for item in items {
+                    if !self.evaluate(item, depth + 1)? {
+                        result = false;
+                        break;
+                    }
+                }
+                result
+            }
+            Rule::Or(ref items) => {
+                let mut result = false;
+                for item in items {
+                    if self.evaluate(item, depth + 1)? {
+                        result = true;
+                        break;
+                    }
+                }
+                result
+            }
+        };
+
+        self.trace[index].result = result;
+        Ok(result)
+    }
+
+    fn generic(&self, key: &str, value: &str) -> bool {
+        let expected = match format_target(value, self.target) {
+            Some(expected) => expected,
+            None => return false,
+        };
+        if let Some(literal) = literal(key) {
+            return literal == expected;
+        }
+        match self.credentials.get(key) {
+            Some(Value::Array(ref items)) => items.iter().any(|item| to_python(item) == expected),
+            Some(actual) => to_python(actual) == expected,
+            None => false,
+        }
+    }
+}
+
+/// Find a value by its key or a dotted path.
+fn lookup<'v>(values: &'v Map<String, Value>, path: &str) -> Option<&'v Value> {
+    if let Some(value) = values.get(path) {
+        return Some(value);
+    }
+    let mut parts = path.split('.');
+    let mut current = values.get(parts.next()?)?;
+    for part in parts {
+        current = current.as_object()?.get(part)?;
+    }
+    Some(current)
+}
+
+/// Substitute `%(name)s` with target values, `None` if a value is missing.
+fn format_target(template: &str, target: &Value) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(tail) = rest.strip_prefix("%%") {
+            result.push('%');
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("%(") {
+            let end = tail.find(")s")?;
+            let value = lookup(target.as_object()?, &tail[..end])?;
+            result.push_str(&to_python(value));
+            rest = &tail[end + 2..];
+        } else {
+            result.push('%');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+/// A literal on the left side of a generic check as a string.
+fn literal(key: &str) -> Option<String> {
+    let quoted = key.len() >= 2
+        && ((key.starts_with('\'') && key.ends_with('\''))
+            || (key.starts_with('"') && key.ends_with('"')));
+    if quoted {
+        Some(key[1..key.len() - 1].to_string())
+    } else if key == "True" || key == "False" || key == "None" {
+        Some(key.to_string())
+    } else if let Ok(number) = key.parse::<i64>() {
+        Some(number.to_string())
+    } else {
+        None
+    }
+}
+
+/// String representation of a value as produced by Python `str`.
+fn to_python(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::String(ref value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl Decision {
+    /// Human-readable trace, one indented check per line.
+    pub fn format_trace(&self) -> String {
+        self.trace
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}{} => {}",
+                    "  ".repeat(entry.depth),
+                    entry.check,
+                    if entry.result { "allow" } else { "deny" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyError::InvalidFormat(ref message) => {
+                write!(f, "Invalid policy file: {}", message)
+            }
+            PolicyError::InvalidRule {
+                name: Some(ref name),
+                ref rule,
+                ref message,
+            } => write!(f, "Invalid rule {} ({}): {}", name, rule, message),
+            PolicyError::InvalidRule {
+                name: None,
+                ref rule,
+                ref message,
+            } => write!(f, "Invalid rule {}: {}", rule, message),
+            PolicyError::RuleNotFound(ref name) => {
+                write!(f, "Rule {} not found and no default rule", name)
+            }
+            PolicyError::RecursiveRule(ref names) => {
+                write!(f, "Recursive rule reference: {}", names.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for PolicyError {}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::super::identity::TokenRoot;
+    use super::*;
+
+    const POLICY_YAML: &str = r#"
+"admin_required": "role:admin or is_admin:1"
+"service_role": "role:service"
+"owner": "user_id:%(user_id)s"
+"admin_or_owner": "rule:admin_required or rule:owner"
+"default": "rule:admin_required"
+"identity:get_user": "rule:admin_or_owner"
+"identity:list_projects": "role:reader and (project_id:%(target.project.id)s or rule:admin_required)"
+"identity:update_project": "not role:reader and project_id:%(target.project.id)s"
+"identity:get_domain": "domain_id:%(target.domain.id)s or token.project.domain.id:%(target.domain.id)s"
+"identity:enabled_only": "True:%(enabled)s"
+"identity:anyone": ""
+"identity:nobody": "!"
+"identity:remote": "http://policy.example.com/check"
+"#;
+
+    const POLICY_JSON: &str = r#"
+{
+    "admin_required": [["role:admin"], ["is_admin:1"]],
+    "context_is_admin": "role:admin",
+    "always": [],
+    "owner": [["user_id:%(user_id)s", "role:member"]]
+}"#;
+
+    const TOKEN: &str = r#"
+{
+    "token": {
+        "expires_at": "2015-11-07T02:58:43.578887Z",
+        "issued_at": "2015-11-07T01:58:43.578929Z",
+        "methods": ["password"],
+        "roles": [
+            {"id": "51cc68", "name": "Reader"},
+            {"id": "2cbc4e", "name": "member"}
+        ],
+        "user": {
+            "domain": {"id": "default", "name": "Default"},
+            "id": "ee4dfb",
+            "name": "demo"
+        },
+        "project": {
+            "domain": {"id": "default", "name": "Default"},
+            "id": "5b50ef",
+            "name": "demo"
+        }
+    }
+}"#;
+
+    fn credentials() -> Credentials {
+        let token = serde_json::from_str::<TokenRoot>(TOKEN).unwrap().token;
+        Credentials::from_token(&token)
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let rule: Rule = "role:admin or (project_id:%(project_id)s and rule:owner)"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule,
+            Rule::Or(vec![
+                Rule::Role("admin".to_string()),
+                Rule::And(vec![
+                    Rule::Generic {
+                        key: "project_id".to_string(),
+                        value: "%(project_id)s".to_string()
+                    },
+                    Rule::Reference("owner".to_string()),
+                ]),
+            ])
+        );
+        assert_eq!(
+            rule.to_string(),
+            "role:admin or (project_id:%(project_id)s and rule:owner)"
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_precedence() {
+        let rule: Rule = "not role:a AND role:b or ((@))".parse().unwrap();
+        assert_eq!(
+            rule,
+            Rule::Or(vec![
+                Rule::And(vec![
+                    Rule::Not(Box::new(Rule::Role("a".to_string()))),
+                    Rule::Role("b".to_string()),
+                ]),
+                Rule::Always,
+            ])
+        );
+        assert_eq!("".parse::<Rule>().unwrap(), Rule::Always);
+        assert_eq!("  !  ".parse::<Rule>().unwrap(), Rule::Never);
+        assert_eq!(
+            "not (role:a or role:b)"
+                .parse::<Rule>()
+                .unwrap()
+                .to_string(),
+            "not (role:a or role:b)"
+        );
+    }
+
+    #[test]
+    fn test_parse_rule_errors() {
+        for rule in &[
+            "role:admin or",
+            "(role:admin",
+            "role:admin)",
+            "admin",
+            "role:a and and role:b",
+            ":value",
+        ] {
+            assert!(
+                matches!(
+                    rule.parse::<Rule>(),
+                    Err(PolicyError::InvalidRule { name: None, .. })
+                ),
+                "{} was parsed",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn test_policy_yaml() {
+        let policy = Policy::parse(POLICY_YAML).unwrap();
+        let creds = credentials();
+
+        let decision = policy
+            .enforce("identity:get_user", &json!({"user_id": "ee4dfb"}), &creds)
+            .unwrap();
+        assert!(decision.allowed);
+        assert_eq!(
+            decision.trace,
+            vec![
+                TraceEntry {
+                    depth: 0,
+                    check: "rule:identity:get_user".to_string(),
+                    result: true
+                },
+                TraceEntry {
+                    depth: 1,
+                    check: "rule:admin_or_owner".to_string(),
+                    result: true
+                },
+                TraceEntry {
+                    depth: 2,
+                    check: "rule:admin_required or rule:owner".to_string(),
+                    result: true
+                },
+                TraceEntry {
+                    depth: 3,
+                    check: "rule:admin_required".to_string(),
+                    result: false
+                },
+                TraceEntry {
+                    depth: 4,
+                    check: "role:admin or is_admin:1".to_string(),
+                    result: false
+                },
+                TraceEntry {
+                    depth: 5,
+                    check: "role:admin".to_string(),
+                    result: false
+                },
+                TraceEntry {
+                    depth: 5,
+                    check: "is_admin:1".to_string(),
+                    result: false
+                },
+                TraceEntry {
+                    depth: 3,
+                    check: "rule:owner".to_string(),
+                    result: true
+                },
+                TraceEntry {
+                    depth: 4,
+                    check: "user_id:%(user_id)s".to_string(),
+                    result: true
+                },
+            ]
+        );
+        assert!(decision
+            .format_trace()
+            .starts_with("rule:identity:get_user => allow\n  rule:admin_or_owner => allow"));
+
+        let decision = policy
+            .enforce("identity:get_user", &json!({"user_id": "other"}), &creds)
+            .unwrap();
+        assert!(!decision.allowed);
+        // Missing target values deny.
+        assert!(
+            !policy
+                .enforce("identity:get_user", &json!({}), &creds)
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[test]
+    fn test_policy_roles_and_targets() {
+        let policy = Policy::parse(POLICY_YAML).unwrap();
+        let creds = credentials();
+        let enforce =
+            |name: &str, target: Value| policy.enforce(name, &target, &creds).unwrap().allowed;
+
+        // Role names are case-insensitive, flat dotted keys and nested objects both work.
+        let nested = json!({"target": {"project": {"id": "5b50ef"}}});
+        let flat = json!({"target.project.id": "5b50ef"});
+        let other = json!({"target.project.id": "other"});
+        assert!(enforce("identity:list_projects", nested.clone()));
+        assert!(enforce("identity:list_projects", flat));
+        assert!(!enforce("identity:list_projects", other));
+        assert!(!enforce("identity:update_project", nested));
+
+        assert!(enforce("identity:enabled_only", json!({"enabled": true})));
+        assert!(!enforce("identity:enabled_only", json!({"enabled": false})));
+        assert!(enforce("identity:anyone", json!({})));
+        assert!(!enforce("identity:nobody", json!({})));
+        assert!(!enforce("identity:remote", json!({})));
+        // Unknown rules use the default rule.
+        assert!(!enforce("identity:unknown", json!({})));
+
+        let mut creds = credentials();
+        creds.insert("token", json!({"project": {"domain": {"id": "default"}}}));
+        assert!(
+            policy
+                .enforce(
+                    "identity:get_domain",
+                    &json!({"target.domain.id": "default"}),
+                    &creds
+                )
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[test]
+    fn test_policy_json() {
+        let policy = Policy::parse(POLICY_JSON).unwrap();
+        assert_eq!(
+            policy.rules["admin_required"].to_string(),
+            "role:admin or is_admin:1"
+        );
+        assert_eq!(policy.rules["always"], Rule::Always);
+
+        let creds = credentials();
+        assert!(
+            policy
+                .enforce("owner", &json!({"user_id": "ee4dfb"}), &creds)
+                .unwrap()
+                .allowed
+        );
+
+        let mut admin = Credentials::new();
+        admin.insert("roles", vec!["admin"]);
+        assert!(
+            policy
+                .enforce("context_is_admin", &json!({}), &admin)
+                .unwrap()
+                .allowed
+        );
+        // No default rule in this file.
+        assert_eq!(
+            policy.enforce("unknown", &json!({}), &admin).unwrap_err(),
+            PolicyError::RuleNotFound("unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        assert!(matches!(
+            Policy::parse("{\"a\": 42}"),
+            Err(PolicyError::InvalidFormat(..))
+        ));
+        match Policy::parse("\"a\": \"role:admin and\"") {
+            Err(PolicyError::InvalidRule { name, .. }) => assert_eq!(name.unwrap(), "a"),
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let policy = Policy::parse(
+            r#"
+"a": "rule:b"
+"b": "role:x or rule:a"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy
+                .enforce("a", &json!({}), &Credentials::new())
+                .unwrap_err(),
+            PolicyError::RecursiveRule(vec!["a".to_string(), "b".to_string(), "a".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_credentials_from_token() {
+        let creds = credentials();
+        assert_eq!(creds.roles(), vec!["Reader", "member"]);
+        assert_eq!(creds.get("project_id"), Some(&json!("5b50ef")));
+        assert_eq!(creds.get("domain_id"), Some(&Value::Null));
+        assert_eq!(creds.get("system_scope"), Some(&Value::Null));
+        assert_eq!(creds.get("is_admin_project"), Some(&json!(true)));
+
+        let mut token = serde_json::from_str::<TokenRoot>(TOKEN).unwrap().token;
+        token.is_admin_project = Some(false);
+        let creds = Credentials::from_token(&token);
+        assert_eq!(creds.get("is_admin_project"), Some(&json!(false)));
+    }
+}
@@ -0,0 +1,372 @@
+// Copyright 2019 Dmitry Tantsur <divius.inside@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Service types from the OpenStack service-types-authority.
+//!
+//! The table is a hand-maintained subset of the published authority data
+//! (<https://service-types.openstack.org/service-types.json>), not a generated copy. Types
+//! missing from it are not an error, but they only match themselves.
+//!
+//! Service types are compared after replacing underscores with dashes, so `object_store` and
+//! `object-store` are the same type.
+
+/// An official service type with its historical aliases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServiceType {
+    /// Official service type.
+    pub service_type: &'static str,
+    /// Name of the project implementing the service.
+    pub project: &'static str,
+    /// Aliases from the most to the least preferred.
+    pub aliases: &'static [&'static str],
+}
+
+const SERVICE_TYPES: &[ServiceType] = &[
+    ServiceType {
+        service_type: "accelerator",
+        project: "cyborg",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "alarming",
+        project: "aodh",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "application-catalog",
+        project: "murano",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "application-container",
+        project: "zun",
+        aliases: &["container"],
+    },
+    ServiceType {
+        service_type: "backup",
+        project: "freezer",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "baremetal",
+        project: "ironic",
+        aliases: &["bare-metal"],
+    },
+    ServiceType {
+        service_type: "baremetal-introspection",
+        project: "ironic-inspector",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "block-storage",
+        project: "cinder",
+        aliases: &["volumev3", "volumev2", "volume", "block-store"],
+    },
+    ServiceType {
+        service_type: "clustering",
+        project: "senlin",
+        aliases: &["resource-cluster", "cluster"],
+    },
+    ServiceType {
+        service_type: "compute",
+        project: "nova",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "container-infrastructure-management",
+        project: "magnum",
+        aliases: &["container-infrastructure", "container-infra"],
+    },
+    ServiceType {
+        service_type: "data-processing",
+        project: "sahara",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "data-protection-orchestration",
+        project: "karbor",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "database",
+        project: "trove",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "dns",
+        project: "designate",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "ec2-api",
+        project: "ec2-api",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "event",
+        project: "panko",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "function-engine",
+        project: "qinling",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "identity",
+        project: "keystone",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "image",
+        project: "glance",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "instance-ha",
+        project: "masakari",
+        aliases: &["ha"],
+    },
+    ServiceType {
+        service_type: "key-manager",
+        project: "barbican",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "load-balancer",
+        project: "octavia",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "message",
+        project: "zaqar",
+        aliases: &["messaging"],
+    },
+    ServiceType {
+        service_type: "metric",
+        project: "gnocchi",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "monitoring",
+        project: "monasca-api",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "network",
+        project: "neutron",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "nfv-orchestration",
+        project: "tacker",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "object-store",
+        project: "swift",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "orchestration",
+        project: "heat",
+        aliases: &[],
+    },
+    // A secondary service of heat, kept after orchestration so that find_by_project returns the
+    // primary one.
+    ServiceType {
+        service_type: "cloudformation",
+        project: "heat",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "placement",
+        project: "placement",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "policy",
+        project: "congress",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "rating",
+        project: "cloudkitty",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "reservation",
+        project: "blazar",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "resource-optimization",
+        project: "watcher",
+        aliases: &["infra-optim"],
+    },
+    ServiceType {
+        service_type: "root-cause-analysis",
+        project: "vitrage",
+        aliases: &["rca"],
+    },
+    ServiceType {
+        service_type: "search",
+        project: "searchlight",
+        aliases: &[],
+    },
+    ServiceType {
+        service_type: "shared-file-system",
+        project: "manila",
+        aliases: &["sharev2", "share"],
+    },
+    ServiceType {
+        service_type: "workflow",
+        project: "mistral",
+        aliases: &["workflowv2"],
+    },
+];
+
+impl ServiceType {
+    /// Whether the given type is the official type or one of the aliases.
+    pub fn matches(&self, service_type: &str) -> bool {
+        let normalized = normalize(service_type);
+        self.service_type == normalized || self.aliases.iter().any(|alias| *alias == normalized)
+    }
+}
+
+/// All known official service types.
+#[inline]
+pub fn all() -> &'static [ServiceType] {
+    SERVICE_TYPES
+}
+
+/// Find the service type by its official type or an alias.
+pub fn find(service_type: &str) -> Option<&'static ServiceType> {
+    SERVICE_TYPES.iter().find(|item| item.matches(service_type))
+}
+
+/// Find the service type by the name of the project implementing it.
+pub fn find_by_project(project: &str) -> Option<&'static ServiceType> {
+    SERVICE_TYPES.iter().find(|item| item.project == project)
+}
+
+/// Service types to look for in a catalog, from the most to the least preferred.
+///
+/// The requested type (normalized) always goes first, followed by the official type and then
+/// by the aliases in the order of the service-types-authority. Unknown types only match
+/// themselves.
+pub fn candidates(service_type: &str) -> Vec<String> {
+    let mut result = vec![normalize(service_type)];
+    if let Some(found) = find(service_type) {
+        for item in Some(&found.service_type).into_iter().chain(found.aliases) {
+            if !result.iter().any(|existing| existing == item) {
+                result.push((*item).to_string());
+            }
+        }
+    }
+    result
+}
+
+/// Whether two service types refer to the same service.
+pub fn is_same(first: &str, second: &str) -> bool {
+    match find(first) {
+        Some(found) => found.matches(second),
+        None => normalize(first) == normalize(second),
+    }
+}
+
+/// Normalize a service type by replacing underscores with dashes.
+#[inline]
+pub fn normalize(service_type: &str) -> String {
+    service_type.replace('_', "-")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("compute").unwrap().project, "nova");
+        assert_eq!(find("volumev3").unwrap().service_type, "block-storage");
+        assert_eq!(find("block_storage").unwrap().project, "cinder");
+        assert_eq!(find("sharev2").unwrap().service_type, "shared-file-system");
+        assert_eq!(find("cloudformation").unwrap().project, "heat");
+        assert_eq!(find("ec2_api").unwrap().project, "ec2-api");
+        assert!(find("compute-legacy").is_none());
+    }
+
+    #[test]
+    fn test_find_by_project() {
+        assert_eq!(find_by_project("ironic").unwrap().service_type, "baremetal");
+        assert_eq!(
+            find_by_project("heat").unwrap().service_type,
+            "orchestration"
+        );
+        assert!(find_by_project("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_candidates_official() {
+        assert_eq!(
+            candidates("block-storage"),
+            vec![
+                "block-storage",
+                "volumev3",
+                "volumev2",
+                "volume",
+                "block-store"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_candidates_alias() {
+        assert_eq!(
+            candidates("volumev2"),
+            vec![
+                "volumev2",
+                "block-storage",
+                "volumev3",
+                "volume",
+                "block-store"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_candidates_unknown() {
+        assert_eq!(candidates("compute_legacy"), vec!["compute-legacy"]);
+    }
+
+    #[test]
+    fn test_is_same() {
+        assert!(is_same("object_store", "object-store"));
+        assert!(is_same("volume", "block-storage"));
+        assert!(is_same("volumev3", "volumev2"));
+        assert!(is_same("compute_legacy", "compute-legacy"));
+        assert!(!is_same("compute", "compute-legacy"));
+        assert!(!is_same("network", "block-storage"));
+    }
+
+    #[test]
+    fn test_all_unique() {
+        for item in all() {
+            assert_eq!(find(item.service_type), Some(item));
+            for alias in item.aliases {
+                assert_eq!(find(alias), Some(item));
+            }
+        }
+    }
+}